@@ -2,138 +2,182 @@ use clap::Parser;
 use log::LevelFilter;
 use std::env;
 
-use dslib::pynode::{ PyNodeFactory };
-use dslib::test::{ TestResult, TestSuite };
-
 #[path = "../../../utils/utils.rs"]
 mod utils;
+mod suite;
 
-// TESTS -----------------------------------------------------------------------
+// MAIN ------------------------------------------------------------------------
 
-fn test_simple(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+#[derive(Parser, Debug)]
+#[clap(about, long_about = None)]
+struct Args {
+    /// Path to Python file with nodes implementations
+    #[clap(long = "impl", short = 'i', default_value = "../main.py")]
+    impl_path: String,
 
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(0..2));
-    }
+    /// Random seed used in tests
+    #[clap(long, short, default_value = "42")]
+    seed: u64,
 
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Nodes count
+    #[clap(long = "nodes", short = 'n', default_value = "6")]
+    node_count: u32,
 
-    sys.step_until_no_events();
+    /// Number of faulty nodes
+    #[clap(long = "faulty_count", short = 'f', default_value = "1")]
+    faulty_count: u32,
 
-    utils::check_consensus(&mut sys, &nodes, None)
-}
+    /// Test to run (optional)
+    #[clap(long, short)]
+    test: Option<String>,
 
-fn test_crash_on_start(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+    /// Suite config (TOML) with the (n, f) pairs, seeds and delays to run every test with,
+    /// see utils/matrix.rs
+    #[clap(long)]
+    config: Option<String>,
 
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(0..2));
-    }
+    /// Run every test with this many seeds, starting from --seed
+    #[clap(long = "seeds", conflicts_with = "seed-range")]
+    seed_count: Option<u64>,
 
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Run every test with the seeds from this range, a..b or a..=b
+    #[clap(long = "seed-range")]
+    seed_range: Option<String>,
 
-    for _ in 0..config.faulty_count {
-        let crashed = sys.gen_range(0..config.node_count).to_string();
-        sys.crash_node(&crashed);
-    }
+    /// Shrink the first failure of every test to a smaller failing scenario
+    #[clap(long)]
+    shrink: bool,
 
-    sys.step_until_no_events();
+    /// Init values and faults of the tests, as JSON reported by --shrink
+    #[clap(long)]
+    overrides: Option<String>,
 
-    let mut alive_nodes = Vec::<String>::new();
-    for node in nodes.iter() {
-        if sys.node_is_crashed(&node) {
-            continue;
-        }
-        alive_nodes.push(node.to_string());
-    }
+    /// Fault timeline for TEST FAULT TIMELINE: a .toml/.json file or inline,
+    /// e.g. "t=1.0 crash 2; t=2.5 disconnect 3; t=4 partition {0,1}|{2,3}; t=8 heal"
+    #[clap(long)]
+    faults: Option<String>,
 
-    utils::check_consensus(&mut sys, &alive_nodes, None)
-}
+    /// Drop rate for messages
+    #[clap(long = "drop", short = 'd', default_value = "0.0")]
+    drop_rate: f64,
 
-fn test_crash_cascade(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+    /// Duplication rate for messages, a duplicated message is delivered 2 or 3 times
+    #[clap(long = "dupl", default_value = "0.0")]
+    dupl_rate: f64,
 
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(0..2));
-    }
+    /// Message delays, min..max or a single delay
+    #[clap(long)]
+    delays: Option<String>,
 
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Write every event of the runs to this JSON lines trace file
+    #[clap(long)]
+    trace: Option<String>,
 
-    for _ in 0..config.faulty_count {
-        let steps_duration = sys.gen_range(1..4) as f64;
-        sys.step_for_duration(steps_duration);
-        let crashed = sys.gen_range(0..config.node_count).to_string();
-        sys.crash_node(&crashed);
-    }
+    /// Rerun the tests of a trace file with its arguments and check that they produce the same trace
+    #[clap(long)]
+    replay: Option<String>,
 
-    sys.step_until_no_events();
+    /// Write a report of the tests to this file, JUnit XML for *.xml and JSON otherwise
+    #[clap(long)]
+    report: Option<String>,
 
-    let mut alive_nodes = Vec::<String>::new();
-    for node in nodes.iter() {
-        if sys.node_is_crashed(&node) {
-            continue;
-        }
-        alive_nodes.push(node.to_string());
-    }
+    /// Run the parameter sweep described by this JSON grid file instead of tests
+    #[clap(long)]
+    sweep: Option<String>,
 
-    utils::check_consensus(&mut sys, &alive_nodes, None)
-}
+    /// Sweep output file, CSV for *.csv and JSON lines otherwise (stdout by default)
+    #[clap(long = "sweep-output")]
+    sweep_output: Option<String>,
 
-// MAIN ------------------------------------------------------------------------
+    /// Number of parallel sweep workers (number of CPUs by default)
+    #[clap(long, short)]
+    jobs: Option<usize>,
 
-#[derive(Parser, Debug)]
-#[clap(about, long_about = None)]
-struct Args {
-    /// Path to Python file with nodes implementations
-    #[clap(long = "impl", short = 'i', default_value = "../main.py")]
-    impl_path: String,
+    /// Re-run the experiment stored in this baseline file and fail on performance regressions
+    #[clap(long)]
+    regression: Option<String>,
 
-    /// Random seed used in tests
-    #[clap(long, short, default_value = "42")]
-    seed: u64,
+    /// Run the --sweep grid and store the results as a baseline file instead of rows
+    #[clap(long = "write-baseline", requires = "sweep")]
+    write_baseline: Option<String>,
 
-    /// Nodes count
-    #[clap(long = "nodes", short = 'n', default_value = "6")]
-    node_count: u32,
+    /// Allowed relative increase of rounds and latency in the regression check
+    #[clap(long, default_value = "0.1")]
+    tolerance: f64,
 
-    /// Number of faulty nodes
-    #[clap(long = "faulty_count", short = 'f', default_value = "1")]
-    faulty_count: u32,
+    /// Significance level of the regression check
+    #[clap(long, default_value = "0.01")]
+    alpha: f64,
 
-    /// Test to run (optional)
-    #[clap(long, short)]
-    test: Option<String>,
+    /// Internal: run one sweep cell given as JSON
+    #[clap(long = "sweep-worker", hide = true)]
+    sweep_worker: Option<String>,
 }
 
 fn main() {
-    utils::init_logger(LevelFilter::Trace);
     env::set_var("PYTHONPATH", "../../dslib/python");
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // a replay runs with the arguments of the recorded run
+    let recording = args.replay.as_deref().map(utils::trace::load);
+    if let Some(recording) = recording.as_ref() {
+        args = Args::parse_from(&recording.args);
+        args.trace = None;
+        args.report = None;
+    }
 
-    let node_factory = PyNodeFactory::new(&args.impl_path, "BenOrNode");
-    let config = utils::TestConfig {
+    if let Some(cell) = args.sweep_worker.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        utils::sweep::run_worker(cell, &args.impl_path);
+        return;
+    }
+    if let Some(baseline) = args.regression.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        utils::regression::run_and_exit(baseline, &args.impl_path, args.jobs, args.tolerance, args.alpha);
+    }
+    if let Some(grid) = args.sweep.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        if let Some(baseline) = args.write_baseline.as_deref() {
+            utils::regression::write_baseline_and_exit(grid, baseline, &args.impl_path, args.jobs);
+        }
+        utils::sweep::run_and_exit(grid, &args.impl_path, args.jobs, args.sweep_output.as_deref());
+    }
+    let seeds = utils::fuzz::get_seeds(args.seed, args.seed_count, args.seed_range.as_deref())
+        .or_else(|| args.shrink.then(|| vec![args.seed]));
+    utils::init_logger(if seeds.is_some() || recording.is_some() { LevelFilter::Off } else { LevelFilter::Trace });
+    if let Some(path) = args.trace.as_deref() {
+        utils::trace::start(path);
+    }
+    if let Some(path) = args.report.as_deref() {
+        utils::report::start(path);
+    }
+
+    let overrides = args.overrides.as_deref().map(utils::RunOverrides::parse);
+    let faults = args.faults.as_deref().map(utils::faults::FaultTimeline::from_arg);
+    let options = utils::SuiteOptions {
         node_count: args.node_count,
         faulty_count: args.faulty_count,
-        node_factory: &node_factory,
-        byz_node_factory: None,
         seed: args.seed,
-        check_termination: false,
+        overrides: overrides.as_ref(),
+        faults: faults.as_ref(),
+        network: utils::NetworkConfig {
+            drop_rate: args.drop_rate,
+            dupl_rate: args.dupl_rate,
+            delays: args.delays.as_deref().map(utils::NetworkConfig::parse_delays),
+        },
     };
-
-    let mut tests = TestSuite::new();
-    tests.add("TEST SIMPLE", test_simple, config);
-    tests.add("TEST CRASH ON START", test_crash_on_start, config);
-    tests.add("TEST CRASH CASCADE", test_crash_cascade, config);
+    let factories = suite::Factories::new(&args.impl_path);
+    let mut tests = utils::fuzz::SeededTestSuite::new();
+    suite::add_tests(&mut tests, &factories, options);
+    if let Some(path) = args.config.as_deref() {
+        tests.expand(&utils::matrix::SuiteConfig::from_arg(path, seeds.is_some()));
+    }
 
     let test = args.test.as_deref();
-    if test.is_none() {
+    if let Some(recording) = recording {
+        tests.replay(&recording);
+    } else if let Some(seeds) = seeds {
+        tests.run_seeds(&seeds, test, args.shrink);
+    } else if test.is_none() {
         tests.run();
     } else {
         tests.run_test(test.unwrap());
@@ -0,0 +1,164 @@
+
+use dslib::pynode::{ PyNodeFactory };
+use dslib::test::TestResult;
+
+use crate::utils;
+
+// TESTS -----------------------------------------------------------------------
+
+fn test_simple(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    sys.step_until_no_events();
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_crash_on_start(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    for _ in 0..config.faulty_count {
+        let crashed = sys.gen_range(0..config.node_count).to_string();
+        utils::crash_node(&mut sys, config, &crashed);
+    }
+
+    sys.step_until_no_events();
+
+    let mut alive_nodes = Vec::<String>::new();
+    for node in nodes.iter() {
+        if sys.node_is_crashed(&node) {
+            continue;
+        }
+        alive_nodes.push(node.to_string());
+    }
+
+    utils::check_consensus_properties(&mut sys, &alive_nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_crash_cascade(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    for _ in 0..config.faulty_count {
+        let steps_duration = sys.gen_range(1..4) as f64;
+        utils::step_before_fault(&mut sys, config, steps_duration);
+        let crashed = sys.gen_range(0..config.node_count).to_string();
+        utils::crash_node(&mut sys, config, &crashed);
+    }
+
+    sys.step_until_no_events();
+
+    let mut alive_nodes = Vec::<String>::new();
+    for node in nodes.iter() {
+        if sys.node_is_crashed(&node) {
+            continue;
+        }
+        alive_nodes.push(node.to_string());
+    }
+
+    utils::check_consensus_properties(&mut sys, &alive_nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_latency(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+    sys.set_delays(1.0, 5.0);
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    sys.step_until_no_events();
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)?;
+    utils::print_decision_latency(&sys, &nodes);
+    Ok(true)
+}
+
+fn test_fault_timeline(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    let faulty_nodes = utils::run_fault_timeline(&mut sys, config)?;
+    let correct_nodes: Vec<String> = nodes.into_iter().filter(|node| !faulty_nodes.contains(node)).collect();
+
+    sys.step_until_no_events();
+
+    utils::check_consensus_properties(&mut sys, &correct_nodes, &init_values, utils::Validity::Binary)
+}
+
+/// Round after which adversarial runs are cut
+const MAX_SCHEDULED_ROUND: u64 = 50;
+
+fn test_split_vote(config: &utils::TestConfig) -> TestResult {
+    let mut scheduler = utils::scheduler::SplitVoteScheduler::default();
+    // BenOrNode decides again when a PROPOSE replaces an earlier one of the
+    // same sender and round (the count stays at 4f + 1), which this schedule
+    // provokes: only the first decision of every node is judged
+    let ignored = [utils::ConsensusProperty::Integrity];
+    utils::scheduler::run_scheduler_test(config, &mut scheduler, MAX_SCHEDULED_ROUND, true, &ignored)
+}
+
+// SUITE -----------------------------------------------------------------------
+
+/// Node classes of the implementation under test.
+pub struct Factories {
+    node: PyNodeFactory,
+}
+
+impl Factories {
+    pub fn new(impl_path: &str) -> Self {
+        Self { node: PyNodeFactory::new(impl_path, "BenOrNode") }
+    }
+}
+
+pub fn add_tests<'a>(tests: &mut utils::fuzz::SeededTestSuite<'a>, factories: &'a Factories, options: utils::SuiteOptions<'a>) {
+    let config = options.config(&factories.node);
+    tests.add("TEST SIMPLE", test_simple, config);
+    tests.add("TEST CRASH ON START", test_crash_on_start, config);
+    tests.add("TEST CRASH CASCADE", test_crash_cascade, config);
+    tests.add("TEST LATENCY", test_latency, config);
+    tests.add("TEST SPLIT VOTE", test_split_vote, config);
+
+    if config.faults.is_some() {
+        tests.add("TEST FAULT TIMELINE", test_fault_timeline, config);
+    }
+}
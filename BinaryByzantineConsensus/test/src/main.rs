@@ -1,237 +1,186 @@
-use assertables::assume;
 use clap::Parser;
 use log::LevelFilter;
 use std::env;
-use rand::prelude::*;
-use rand_pcg::Pcg64;
-
-use dslib::pynode::{ PyNodeFactory };
-use dslib::test::{ TestResult, TestSuite };
 
 #[path = "../../../utils/utils.rs"]
 mod utils;
+mod suite;
 
-// TESTS -----------------------------------------------------------------------
+// MAIN ------------------------------------------------------------------------
 
-fn test_simple(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+#[derive(Parser, Debug)]
+#[clap(about, long_about = None)]
+struct Args {
+    /// Path to Python file with nodes implementations
+    #[clap(long = "impl", short = 'i', default_value = "../main.py")]
+    impl_path: String,
 
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(0..2));
-    }
+    /// Random seed used in tests
+    #[clap(long, short, default_value = "42")]
+    seed: u64,
 
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Nodes count
+    #[clap(long = "nodes", short = 'n', default_value = "4")]
+    node_count: u32,
 
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
+    /// Number of faulty nodes
+    #[clap(long = "faulty_count", short = 'f', default_value = "1")]
+    faulty_count: u32,
 
-    utils::check_consensus(&mut sys, &nodes, None)
-}
+    /// Test to run (optional)
+    #[clap(long, short)]
+    test: Option<String>,
 
-fn test_all_one(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+    /// Suite config (TOML) with the (n, f) pairs, seeds and delays to run every test with,
+    /// see utils/matrix.rs
+    #[clap(long)]
+    config: Option<String>,
 
-    let mut init_values = Vec::new();
-    init_values.resize(nodes.len(), 1);
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Run every test with this many seeds, starting from --seed
+    #[clap(long = "seeds", conflicts_with = "seed-range")]
+    seed_count: Option<u64>,
 
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
+    /// Run every test with the seeds from this range, a..b or a..=b
+    #[clap(long = "seed-range")]
+    seed_range: Option<String>,
 
-    utils::check_consensus(&mut sys, &nodes, Some(1))
-}
+    /// Shrink the first failure of every test to a smaller failing scenario
+    #[clap(long)]
+    shrink: bool,
 
-fn test_all_zero(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+    /// Init values and faults of the tests, as JSON reported by --shrink
+    #[clap(long)]
+    overrides: Option<String>,
 
-    let mut init_values = Vec::new();
-    init_values.resize(nodes.len(), 0);
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Fault timeline for TEST FAULT TIMELINE: a .toml/.json file or inline,
+    /// e.g. "t=1.0 crash 2; t=2.5 disconnect 3; t=4 partition {0,1}|{2,3}; t=8 heal"
+    #[clap(long)]
+    faults: Option<String>,
 
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
+    /// Drop rate for messages
+    #[clap(long = "drop", short = 'd', default_value = "0.0")]
+    drop_rate: f64,
 
-    utils::check_consensus(&mut sys, &nodes, Some(0))
-}
+    /// Duplication rate for messages, a duplicated message is delivered 2 or 3 times
+    #[clap(long = "dupl", default_value = "0.0")]
+    dupl_rate: f64,
 
-fn test_half_half(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+    /// Message delays, min..max or a single delay
+    #[clap(long)]
+    delays: Option<String>,
 
-    let mut init_values = Vec::new();
-    init_values.resize(nodes.len(), 1);
-    let half = nodes.len() / 2;
-    for i in 0..half {
-        init_values[i] = 0;
-    }
+    /// Write every event of the runs to this JSON lines trace file
+    #[clap(long)]
+    trace: Option<String>,
 
-    utils::send_init_messages(&mut sys, &init_values);
+    /// Rerun the tests of a trace file with its arguments and check that they produce the same trace
+    #[clap(long)]
+    replay: Option<String>,
 
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
+    /// Write a report of the tests to this file, JUnit XML for *.xml and JSON otherwise
+    #[clap(long)]
+    report: Option<String>,
 
-    utils::check_consensus(&mut sys, &nodes, None)
-}
+    /// Run the parameter sweep described by this JSON grid file instead of tests
+    #[clap(long)]
+    sweep: Option<String>,
 
-fn test_disconnect_after_init(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
+    /// Sweep output file, CSV for *.csv and JSON lines otherwise (stdout by default)
+    #[clap(long = "sweep-output")]
+    sweep_output: Option<String>,
 
-    let mut init_values = Vec::new();
-    init_values.resize(nodes.len(), 1);
-    let tmp = nodes.len() * 3 / 4;
-    for i in 0..tmp {
-        init_values[i] = 0;
-    }
-    let mut rand = Pcg64::seed_from_u64(config.seed);
-    init_values.shuffle(&mut rand);
+    /// Number of parallel sweep workers (number of CPUs by default)
+    #[clap(long, short)]
+    jobs: Option<usize>,
 
-    utils::send_init_messages(&mut sys, &init_values);
-    sys.step_for_duration(1.0);
+    /// Re-run the experiment stored in this baseline file and fail on performance regressions
+    #[clap(long)]
+    regression: Option<String>,
 
-    let mut correct_nodes = Vec::<String>::new();
-    let mut disconnected_nodes = Vec::<String>::new();
+    /// Run the --sweep grid and store the results as a baseline file instead of rows
+    #[clap(long = "write-baseline", requires = "sweep")]
+    write_baseline: Option<String>,
 
-    for i in 0..config.faulty_count {
-        let node = format!("{}", i);
-        sys.disconnect_node(&node);
-        disconnected_nodes.push(node);
-    }
-    for i in config.faulty_count..config.node_count {
-        let node = format!("{}", i);
-        correct_nodes.push(node);
-    }
+    /// Allowed relative increase of rounds and latency in the regression check
+    #[clap(long, default_value = "0.1")]
+    tolerance: f64,
 
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
+    /// Significance level of the regression check
+    #[clap(long, default_value = "0.01")]
+    alpha: f64,
 
-    assume!(utils::check_not_delivery(&mut sys, &disconnected_nodes).is_ok())?;
-    utils::check_consensus(&mut sys, &correct_nodes, None)
+    /// Internal: run one sweep cell given as JSON
+    #[clap(long = "sweep-worker", hide = true)]
+    sweep_worker: Option<String>,
 }
 
-fn test_print_stat(config: &utils::TestConfig) -> TestResult {
-    let mut percentages = Vec::<u64>::new();
-    percentages.push(25);
-    percentages.push(50);
-    percentages.push(75);
-
-    for seed in 1..=1000 {
-        for percentage_of_ones in percentages.iter() {
-            let mut sys = utils::build_system_with_custom_seed(config, seed);
-            let nodes = sys.get_node_ids();
-
-            sys.set_delays(1.0, 5.0);
-
-            let mut init_values = Vec::new();
-            init_values.resize(nodes.len(), 0);
-
-            for i in 0..nodes.len() {
-                if (i as f32) / (nodes.len() as f32) > (*percentage_of_ones as f32) / 100.0 {
-                    break;
-                }
-                init_values[i] = 1;
-            }
-
-            let mut rand = Pcg64::seed_from_u64(seed);
-            init_values.shuffle(&mut rand);
-
-            utils::send_init_messages(&mut sys, &init_values);
-
-            if config.check_termination {
-                sys.step_until_no_events();
-            }
+fn main() {
+    env::set_var("PYTHONPATH", "../../dslib/python");
+    let mut args = Args::parse();
+    // a replay runs with the arguments of the recorded run
+    let recording = args.replay.as_deref().map(utils::trace::load);
+    if let Some(recording) = recording.as_ref() {
+        args = Args::parse_from(&recording.args);
+        args.trace = None;
+        args.report = None;
+    }
 
-            assume!(
-                utils::check_consensus_and_print_statistics(
-                        &mut sys, &nodes, None,
-                        config, seed, *percentage_of_ones
-                    ).is_ok()
-            )?;
+    if let Some(cell) = args.sweep_worker.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        utils::sweep::run_worker(cell, &args.impl_path);
+        return;
+    }
+    if let Some(baseline) = args.regression.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        utils::regression::run_and_exit(baseline, &args.impl_path, args.jobs, args.tolerance, args.alpha);
+    }
+    if let Some(grid) = args.sweep.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        if let Some(baseline) = args.write_baseline.as_deref() {
+            utils::regression::write_baseline_and_exit(grid, baseline, &args.impl_path, args.jobs);
         }
+        utils::sweep::run_and_exit(grid, &args.impl_path, args.jobs, args.sweep_output.as_deref());
+    }
+    let seeds = utils::fuzz::get_seeds(args.seed, args.seed_count, args.seed_range.as_deref())
+        .or_else(|| args.shrink.then(|| vec![args.seed]));
+    utils::init_logger(if seeds.is_some() || recording.is_some() { LevelFilter::Off } else { LevelFilter::Trace });
+    if let Some(path) = args.trace.as_deref() {
+        utils::trace::start(path);
+    }
+    if let Some(path) = args.report.as_deref() {
+        utils::report::start(path);
     }
 
-    Ok(true)
-}
-
-// MAIN ------------------------------------------------------------------------
-
-#[derive(Parser, Debug)]
-#[clap(about, long_about = None)]
-struct Args {
-    /// Path to Python file with nodes implementations
-    #[clap(long = "impl", short = 'i', default_value = "../main.py")]
-    impl_path: String,
-
-    /// Random seed used in tests
-    #[clap(long, short, default_value = "42")]
-    seed: u64,
-
-    /// Nodes count
-    #[clap(long = "nodes", short = 'n', default_value = "4")]
-    node_count: u32,
-
-    /// Number of faulty nodes
-    #[clap(long = "faulty_count", short = 'f', default_value = "1")]
-    faulty_count: u32,
-
-    /// Test to run (optional)
-    #[clap(long, short)]
-    test: Option<String>,
-}
-
-fn main() {
-    env::set_var("PYTHONPATH", "../../dslib/python");
-    let args = Args::parse();
-
-    let node_factory_safe = PyNodeFactory::new(&args.impl_path, "SafeBBC");
-    let mut config = utils::TestConfig {
+    let overrides = args.overrides.as_deref().map(utils::RunOverrides::parse);
+    let faults = args.faults.as_deref().map(utils::faults::FaultTimeline::from_arg);
+    let options = utils::SuiteOptions {
         node_count: args.node_count,
         faulty_count: args.faulty_count,
-        node_factory: &node_factory_safe,
-        byz_node_factory: None,
         seed: args.seed,
-        check_termination: false,
+        overrides: overrides.as_ref(),
+        faults: faults.as_ref(),
+        network: utils::NetworkConfig {
+            drop_rate: args.drop_rate,
+            dupl_rate: args.dupl_rate,
+            delays: args.delays.as_deref().map(utils::NetworkConfig::parse_delays),
+        },
     };
-
-    let mut tests = TestSuite::new();
-    tests.add("TEST SAFE SIMPLE", test_simple, config);
-    tests.add("TEST SAFE ALL ONE", test_all_one, config);
-    tests.add("TEST SAFE ALL ZERO", test_all_zero, config);
-    tests.add("TEST SAFE HALF/HALF", test_half_half, config);
-    tests.add("TEST SAFE FAULTY", test_disconnect_after_init, config);
-
-    let node_factory_psync = PyNodeFactory::new(&args.impl_path, "PsyncBBC");
-    config.node_factory = &node_factory_psync;
-    config.check_termination = true;
-    tests.add("TEST PSYNC SIMPLE", test_simple, config);
-    tests.add("TEST PSYNC ALL ONE", test_all_one, config);
-    tests.add("TEST PSYNC ALL ZERO", test_all_zero, config);
-    tests.add("TEST PSYNC HALF/HALF", test_half_half, config);
-    tests.add("TEST PSYNC FAULTY", test_disconnect_after_init, config);
+    let factories = suite::Factories::new(&args.impl_path);
+    let mixed = factories.mixed();
+    let mut tests = utils::fuzz::SeededTestSuite::new();
+    suite::add_tests(&mut tests, &factories, &mixed, options);
+    if let Some(path) = args.config.as_deref() {
+        tests.expand(&utils::matrix::SuiteConfig::from_arg(path, seeds.is_some()));
+    }
 
     let test = args.test.as_deref();
-    if test.is_none() {
-        utils::init_logger(LevelFilter::Trace);
+    if let Some(recording) = recording {
+        tests.replay(&recording);
+    } else if let Some(seeds) = seeds {
+        tests.run_seeds(&seeds, test, args.shrink);
+    } else if test.is_none() {
         tests.run();
     } else {
-        if test.unwrap() == "TEST PSYNC PRINT STAT" {
-            utils::init_logger(LevelFilter::Debug);
-            config.node_count = 64;
-            config.faulty_count = 21;
-            tests.add(test.unwrap(), test_print_stat, config);
-            tests.run_test(test.unwrap());
-        } else {
-            utils::init_logger(LevelFilter::Trace);
-            tests.run_test(test.unwrap());
-        }
+        tests.run_test(test.unwrap());
     }
 }
@@ -0,0 +1,338 @@
+use assertables::assume;
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+
+use dslib::pynode::{ JsonMessage, PyNodeFactory };
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use crate::utils;
+use crate::utils::messages::{ MessageResult, ProtocolMessage };
+
+/// Steps the system until it has no events, stopping as soon as two of
+/// `correct_nodes` decide differently.
+fn step_monitored(sys: &mut System<JsonMessage>, correct_nodes: &[String]) -> TestResult {
+    let monitor = utils::monitor::AgreementMonitor::new(correct_nodes);
+    utils::monitor::RunMonitor::new(Box::new(monitor)).step_until_no_events(sys)
+}
+
+// TESTS -----------------------------------------------------------------------
+
+fn test_simple(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        step_monitored(&mut sys, &nodes)?;
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_all_one(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    init_values.resize(nodes.len(), 1);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        step_monitored(&mut sys, &nodes)?;
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_all_zero(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    init_values.resize(nodes.len(), 0);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        step_monitored(&mut sys, &nodes)?;
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_half_half(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    init_values.resize(nodes.len(), 1);
+    let half = nodes.len() / 2;
+    for i in 0..half {
+        init_values[i] = 0;
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        step_monitored(&mut sys, &nodes)?;
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_disconnect_after_init(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    init_values.resize(nodes.len(), 1);
+    let tmp = nodes.len() * 3 / 4;
+    for i in 0..tmp {
+        init_values[i] = 0;
+    }
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    init_values.shuffle(&mut rand);
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+    utils::step_before_fault(&mut sys, config, 1.0);
+
+    let mut correct_nodes = Vec::<String>::new();
+    let mut disconnected_nodes = Vec::<String>::new();
+
+    for i in 0..config.faulty_count {
+        let node = format!("{}", i);
+        utils::disconnect_node(&mut sys, config, &node);
+        disconnected_nodes.push(node);
+    }
+    for i in config.faulty_count..config.node_count {
+        let node = format!("{}", i);
+        correct_nodes.push(node);
+    }
+
+    if config.check_termination {
+        step_monitored(&mut sys, &correct_nodes)?;
+    }
+
+    assume!(utils::check_not_delivery(&mut sys, &disconnected_nodes).is_ok())?;
+    utils::check_consensus_properties(&mut sys, &correct_nodes, &init_values, utils::Validity::Binary)
+}
+
+fn test_latency(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+    sys.set_delays(1.0, 5.0);
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        step_monitored(&mut sys, &nodes)?;
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)?;
+    utils::print_decision_latency(&sys, &nodes);
+    Ok(true)
+}
+
+fn test_fault_timeline(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(0..2));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    let faulty_nodes = utils::run_fault_timeline(&mut sys, config)?;
+    let correct_nodes: Vec<String> = nodes.into_iter().filter(|node| !faulty_nodes.contains(node)).collect();
+
+    if config.check_termination {
+        step_monitored(&mut sys, &correct_nodes)?;
+    }
+
+    utils::check_consensus_properties(&mut sys, &correct_nodes, &init_values, utils::Validity::Binary)
+}
+
+const PARTITION_HEAL_TIME: f64 = 20.0;
+
+fn gen_init_values(sys: &mut System<JsonMessage>) -> Vec<u64> {
+    sys.get_node_ids().iter().map(|_| sys.gen_range(0..2)).collect()
+}
+
+fn test_partition_minority(config: &utils::TestConfig) -> TestResult {
+    let groups = utils::partition::minority_groups(config.node_count, config.faulty_count);
+    utils::partition::run_partition_test(
+        config, &groups, PARTITION_HEAL_TIME, gen_init_values, utils::Validity::Binary
+    )
+}
+
+fn test_partition_split(config: &utils::TestConfig) -> TestResult {
+    let groups = utils::partition::split_groups(config.node_count);
+    utils::partition::run_partition_test(
+        config, &groups, PARTITION_HEAL_TIME, gen_init_values, utils::Validity::Binary
+    )
+}
+
+fn test_partition_coordinator(config: &utils::TestConfig) -> TestResult {
+    let groups = utils::partition::coordinator_groups(1, config.node_count);
+    utils::partition::run_partition_test(
+        config, &groups, PARTITION_HEAL_TIME, gen_init_values, utils::Validity::Binary
+    )
+}
+
+/// (GST, delta) pairs of `test_gst`
+const GST_CASES: [(f64, f64); 4] = [(10.0, 1.0), (20.0, 1.0), (20.0, 3.0), (50.0, 2.0)];
+
+fn test_gst(config: &utils::TestConfig) -> TestResult {
+    for (gst, delta) in GST_CASES {
+        let model = utils::gst::GstModel { gst, delta, pre_gst_delay: gst };
+        let factory = utils::gst::GstFactory::new(config.node_factory, model);
+        let mut gst_config = *config;
+        gst_config.node_factory = &factory;
+        gst_config.network.delays = Some(model.network_delays());
+
+        let mut sys = utils::build_system(&gst_config);
+        let nodes = sys.get_node_ids();
+        let init_values = gen_init_values(&mut sys);
+        let init_values = utils::get_init_values(config, init_values);
+        utils::send_init_messages(&mut sys, &init_values);
+
+        step_monitored(&mut sys, &nodes).map_err(|e| format!("GST {} delta {}: {}", gst, delta, e))?;
+
+        utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::Binary)
+            .map_err(|e| format!("GST {} delta {}: {}", gst, delta, e))?;
+        let decision_round = nodes.iter()
+            .filter_map(|node| {
+                let messages = utils::get_local_messages(&sys, node);
+                MessageResult::decode(messages.first()?).ok()?.round
+            })
+            .max()
+            .unwrap_or(0);
+        let latency = utils::get_decision_latencies(&sys, &nodes).iter().map(|(_, l)| *l).fold(0.0, f64::max);
+        println!(
+            "gst: gst={} delta={} rounds_before_gst={} decision_round={} last_decision={:.3}",
+            gst, delta, utils::rounds_before(gst), decision_round, latency
+        );
+    }
+    Ok(true)
+}
+
+/// Bounds of the coordinator delay adversary in `test_coord_delay`
+const COORD_DELAYS: [f64; 3] = [2.0, 4.0, 8.0];
+/// Round after which a run against the adversary is cut
+const MAX_COORD_DELAY_ROUND: u64 = 50;
+
+fn test_coord_delay(config: &utils::TestConfig) -> TestResult {
+    utils::adversary::run_coord_delay_test(config, &COORD_DELAYS, MAX_COORD_DELAY_ROUND, gen_init_values, utils::Validity::Binary)
+}
+
+/// Round after which adversarial runs are cut
+const MAX_SCHEDULED_ROUND: u64 = 50;
+
+fn test_split_vote(config: &utils::TestConfig) -> TestResult {
+    let mut scheduler = utils::scheduler::SplitVoteScheduler::default();
+    utils::scheduler::run_scheduler_test(config, &mut scheduler, MAX_SCHEDULED_ROUND, config.check_termination, &[])
+}
+
+/// Number of deliveries whose order `test_explore` enumerates
+const EXPLORE_DEPTH: usize = 4;
+const MAX_EXPLORED_ROUND: u64 = 10;
+
+fn test_explore(config: &utils::TestConfig) -> TestResult {
+    let mut small_config = *config;
+    small_config.node_count = 4;
+    small_config.faulty_count = 1;
+    let bounds = utils::explore::ExploreConfig {
+        depth: EXPLORE_DEPTH,
+        max_round: MAX_EXPLORED_ROUND,
+        until_decided: true,
+    };
+    let nodes: Vec<String> = (0..small_config.node_count).map(|id| id.to_string()).collect();
+
+    let init_values = utils::get_init_values(config, vec![0, 1, 0, 1]);
+    utils::explore::explore(
+        &small_config,
+        bounds,
+        |sys| utils::send_init_messages(sys, &init_values),
+        |sys| utils::check_consensus_safety(sys, &nodes, &init_values, utils::Validity::Binary),
+    )
+}
+
+// SUITE -----------------------------------------------------------------------
+
+/// Node classes of the implementation under test.
+pub struct Factories {
+    safe: PyNodeFactory,
+    psync: PyNodeFactory,
+}
+
+impl Factories {
+    pub fn new(impl_path: &str) -> Self {
+        Self {
+            safe: PyNodeFactory::new(impl_path, "SafeBBC"),
+            psync: PyNodeFactory::new(impl_path, "PsyncBBC"),
+        }
+    }
+
+    /// `node_factories` of the mixed tests: SafeBBC on node 0, which is kept
+    /// when the shrinker reduces the node count, PsyncBBC on the others.
+    pub fn mixed(&self) -> [(u32, &dyn utils::NodeFactory); 1] {
+        [(0, &self.safe)]
+    }
+}
+
+pub fn add_tests<'a>(
+    tests: &mut utils::fuzz::SeededTestSuite<'a>,
+    factories: &'a Factories,
+    mixed: &'a [(u32, &'a dyn utils::NodeFactory)],
+    options: utils::SuiteOptions<'a>
+) {
+    let mut config = options.config(&factories.safe);
+    tests.add("TEST SAFE SIMPLE", test_simple, config);
+    tests.add("TEST SAFE ALL ONE", test_all_one, config);
+    tests.add("TEST SAFE ALL ZERO", test_all_zero, config);
+    tests.add("TEST SAFE HALF/HALF", test_half_half, config);
+    tests.add("TEST SAFE FAULTY", test_disconnect_after_init, config);
+    tests.add("TEST SAFE LATENCY", test_latency, config);
+    tests.add("TEST SAFE SPLIT VOTE", test_split_vote, config);
+    tests.add("TEST SAFE EXPLORE", test_explore, config);
+
+    config.node_factory = &factories.psync;
+    config.check_termination = true;
+    tests.add("TEST PSYNC SIMPLE", test_simple, config);
+    tests.add("TEST PSYNC ALL ONE", test_all_one, config);
+    tests.add("TEST PSYNC ALL ZERO", test_all_zero, config);
+    tests.add("TEST PSYNC HALF/HALF", test_half_half, config);
+    tests.add("TEST PSYNC FAULTY", test_disconnect_after_init, config);
+    tests.add("TEST PSYNC LATENCY", test_latency, config);
+    tests.add("TEST PSYNC PARTITION MINORITY", test_partition_minority, config);
+    tests.add("TEST PSYNC PARTITION SPLIT", test_partition_split, config);
+    tests.add("TEST PSYNC PARTITION COORDINATOR", test_partition_coordinator, config);
+    tests.add("TEST PSYNC GST", test_gst, config);
+    tests.add("TEST PSYNC COORD DELAY", test_coord_delay, config);
+    if config.faults.is_some() {
+        tests.add("TEST PSYNC FAULT TIMELINE", test_fault_timeline, config);
+    }
+
+    config.node_factories = mixed;
+    config.check_termination = false;
+    tests.add("TEST MIXED SIMPLE", test_simple, config);
+    tests.add("TEST MIXED HALF/HALF", test_half_half, config);
+}
@@ -0,0 +1,242 @@
+use assertables::assume;
+
+use dslib::pynode::{ JsonMessage, PyNodeFactory };
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use crate::utils;
+use crate::utils::messages::{ MessageDelivery, ProtocolMessage };
+
+static DELIVERED: &str = "DELIVERY";
+static DUPLICATION_RATE: f64 = 1.0;
+static EXPLORE_DEPTH: usize = 5;
+
+/// Steps the system until it has no events, stopping as soon as one of
+/// `correct_nodes` delivers a value which none of them proposed.
+fn step_monitored(sys: &mut System<JsonMessage>, correct_nodes: &[String]) -> TestResult {
+    let monitor = utils::monitor::BvMonitor::new(correct_nodes, DELIVERED);
+    utils::monitor::RunMonitor::new(Box::new(monitor)).step_until_no_events(sys)
+}
+
+// TESTS -----------------------------------------------------------------------
+
+fn test_simple(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let bin_value: u64 = 1;
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(bin_value);
+    }
+
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes)?;
+
+    utils::check_delivery(&mut sys, DELIVERED, &nodes, Some(bin_value))
+}
+
+fn test_min_init(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let bin_value: u64 = 1;
+
+    let mut init_values = Vec::new();
+    let min_init_nodes_cnt = config.faulty_count + 1;
+    for _ in 0..min_init_nodes_cnt {
+        init_values.push(bin_value);
+    }
+
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes)?;
+
+    utils::check_delivery(&mut sys, DELIVERED, &nodes, Some(bin_value))
+}
+
+fn test_not_enough_init(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let bin_value: u64 = 1;
+
+    let mut init_values = Vec::new();
+    let init_nodes_cnt = config.faulty_count;
+    for _ in 0..init_nodes_cnt {
+        init_values.push(bin_value);
+    }
+
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes)?;
+
+    utils::check_not_delivery(&mut sys, &nodes)
+}
+
+fn test_disconnect_after_init(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+
+    let bin_value: u64 = 1;
+
+    let mut init_values = Vec::new();
+    let min_init_nodes_cnt = config.faulty_count + 1;
+    for _ in 0..min_init_nodes_cnt {
+        init_values.push(bin_value);
+    }
+
+    utils::send_init_messages(&mut sys, &init_values);
+    utils::step_before_fault(&mut sys, config, 1.0);
+
+    let mut correct_nodes = Vec::<String>::new();
+    let mut disconnected_nodes = Vec::<String>::new();
+
+    for i in 0..config.faulty_count {
+        let node = format!("{}", i);
+        utils::disconnect_node(&mut sys, config, &node);
+        disconnected_nodes.push(node);
+    }
+    for i in config.faulty_count..config.node_count {
+        let node = format!("{}", i);
+        correct_nodes.push(node);
+    }
+
+    let nodes = sys.get_node_ids();
+    step_monitored(&mut sys, &nodes)?;
+
+    assume!(utils::check_not_delivery(&mut sys, &disconnected_nodes).is_ok())?;
+    utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, Some(bin_value))
+}
+
+fn test_diff_inits(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let bin_value: u64 = 1;
+
+    let mut init_values = Vec::new();
+    let init_nodes_cnt = config.faulty_count;
+    for _ in 0..init_nodes_cnt {
+        init_values.push(bin_value);
+    }
+    init_values.push((bin_value + 1) % 2);
+
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes)?;
+
+    utils::check_not_delivery(&mut sys, &nodes)
+}
+
+fn test_message_complexity(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for i in 0..nodes.len() {
+        init_values.push((i % 2) as u64);
+    }
+
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes)?;
+
+    let counter = utils::get_message_counter(&sys);
+    utils::print_message_counts(&counter);
+
+    // every node broadcasts ECHO at most once per binary value
+    let n = config.node_count as u64;
+    let echo_cnt = counter.count_type("ECHO");
+    assume!(
+        echo_cnt <= 2 * n * n,
+        format!("{} ECHO messages sent, expected at most {}", echo_cnt, 2 * n * n)
+    )?;
+    for (sender, cnt) in counter.by_sender() {
+        assume!(cnt <= 2 * n, format!("Node {}: sent {} messages, expected at most {}", sender, cnt, 2 * n))?;
+    }
+    Ok(true)
+}
+
+fn test_fault_timeline(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let bin_value: u64 = 1;
+
+    let init_values = vec![bin_value; nodes.len()];
+
+    utils::send_init_messages(&mut sys, &init_values);
+
+    let faulty_nodes = utils::run_fault_timeline(&mut sys, config)?;
+    let correct_nodes: Vec<String> = nodes.into_iter().filter(|node| !faulty_nodes.contains(node)).collect();
+
+    step_monitored(&mut sys, &correct_nodes)?;
+
+    utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, Some(bin_value))
+}
+
+fn test_explore(config: &utils::TestConfig) -> TestResult {
+    let mut small_config = *config;
+    small_config.node_count = 4;
+    small_config.faulty_count = 1;
+    let bounds = utils::explore::ExploreConfig { depth: EXPLORE_DEPTH, max_round: 0, until_decided: false };
+
+    // 1 is proposed by f + 1 nodes and must be delivered everywhere, 0 by one node only
+    let init_values: Vec<u64> = vec![1, 1, 0];
+    utils::explore::explore(
+        &small_config,
+        bounds,
+        |sys| utils::send_init_messages(sys, &init_values),
+        |sys| {
+            for node in sys.get_node_ids() {
+                let mut values = Vec::new();
+                for msg in utils::get_local_messages(sys, &node) {
+                    assume!(msg.tip == DELIVERED, format!("Node {}: Wrong message type!", node))?;
+                    values.push(MessageDelivery::decode(&msg).map_err(|e| format!("Node {}: {}", node, e))?.value);
+                }
+                assume!(values == vec![1], format!("Node {}: delivered {:?} instead of [1]", node, values))?;
+            }
+            Ok(true)
+        },
+    )
+}
+
+// SUITE -----------------------------------------------------------------------
+
+/// Node classes of the implementation under test.
+pub struct Factories {
+    node: PyNodeFactory,
+}
+
+impl Factories {
+    pub fn new(impl_path: &str) -> Self {
+        Self { node: PyNodeFactory::new(impl_path, "BBNode") }
+    }
+}
+
+pub fn add_tests<'a>(tests: &mut utils::fuzz::SeededTestSuite<'a>, factories: &'a Factories, options: utils::SuiteOptions<'a>) {
+    let mut config = options.config(&factories.node);
+    tests.add("TEST SIMPLE", test_simple, config);
+    tests.add("TEST MIN INIT", test_min_init, config);
+    tests.add("TEST NOT ENOUGH INIT", test_not_enough_init, config);
+    tests.add("TEST DISCONNECT AFTER INIT", test_disconnect_after_init, config);
+    tests.add("TEST DIFF INITS", test_diff_inits, config);
+    tests.add("TEST MESSAGE COMPLEXITY", test_message_complexity, config);
+
+    // every message is delivered 2 or 3 times, a node must count each sender once
+    config.network.dupl_rate = DUPLICATION_RATE;
+    tests.add("TEST DUPLICATION SIMPLE", test_simple, config);
+    tests.add("TEST DUPLICATION MIN INIT", test_min_init, config);
+    tests.add("TEST DUPLICATION NOT ENOUGH INIT", test_not_enough_init, config);
+    tests.add("TEST DUPLICATION DIFF INITS", test_diff_inits, config);
+    tests.add("TEST DUPLICATION MESSAGE COMPLEXITY", test_message_complexity, config);
+    config.network.dupl_rate = options.network.dupl_rate;
+
+    tests.add("TEST EXPLORE", test_explore, config);
+
+    if config.faults.is_some() {
+        tests.add("TEST FAULT TIMELINE", test_fault_timeline, config);
+    }
+}
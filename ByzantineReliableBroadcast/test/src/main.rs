@@ -1,78 +1,10 @@
-use assertables::assume;
 use clap::Parser;
 use log::LevelFilter;
 use std::env;
 
-use dslib::pynode::{ PyNodeFactory };
-use dslib::test::{ TestResult, TestSuite };
-
 #[path = "../../../utils/utils.rs"]
 mod utils;
-
-static DELIVERED: &str = "ACCEPT";
-
-// TESTS -----------------------------------------------------------------------
-
-fn test_simple(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
-
-    let init_value: u64 = 42;
-
-    let mut init_values = Vec::new();
-    init_values.push(init_value);
-    utils::send_init_messages(&mut sys, &init_values);
-
-    sys.step_until_no_events();
-
-    utils::check_delivery(&mut sys, DELIVERED, &nodes, Some(init_value))
-}
-
-fn test_disconnect_after_init(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-
-    let init_value: u64 = 42;
-
-    let mut init_values = Vec::new();
-    init_values.push(init_value);
-    utils::send_init_messages(&mut sys, &init_values);
-
-    sys.step_for_duration(1.0);
-
-    let mut correct_nodes = Vec::<String>::new();
-    let mut disconnected_nodes = Vec::<String>::new();
-
-    for i in 0..config.faulty_count {
-        let node = format!("{}", i);
-        sys.disconnect_node(&node);
-        disconnected_nodes.push(node);
-    }
-    for i in config.faulty_count..config.node_count {
-        let node = format!("{}", i);
-        correct_nodes.push(node);
-    }
-
-    sys.step_until_no_events();
-
-    assume!(utils::check_not_delivery(&mut sys, &disconnected_nodes).is_ok())?;
-    utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, Some(init_value))
-}
-
-
-fn test_byzantine(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system_with_byz(config);
-    let nodes = sys.get_node_ids();
-
-    let init_value: u64 = 42;
-
-    let mut init_values = Vec::new();
-    init_values.push(init_value);
-    utils::send_init_messages(&mut sys, &init_values);
-
-    sys.step_until_no_events();
-
-    utils::check_not_delivery(&mut sys, &nodes)
-}
+mod suite;
 
 // MAIN ------------------------------------------------------------------------
 
@@ -98,31 +30,105 @@ struct Args {
     /// Test to run (optional)
     #[clap(long, short)]
     test: Option<String>,
+
+    /// Suite config (TOML) with the (n, f) pairs, seeds and delays to run every test with,
+    /// see utils/matrix.rs
+    #[clap(long)]
+    config: Option<String>,
+
+    /// Run every test with this many seeds, starting from --seed
+    #[clap(long = "seeds", conflicts_with = "seed-range")]
+    seed_count: Option<u64>,
+
+    /// Run every test with the seeds from this range, a..b or a..=b
+    #[clap(long = "seed-range")]
+    seed_range: Option<String>,
+
+    /// Shrink the first failure of every test to a smaller failing scenario
+    #[clap(long)]
+    shrink: bool,
+
+    /// Init values and faults of the tests, as JSON reported by --shrink
+    #[clap(long)]
+    overrides: Option<String>,
+
+    /// Fault timeline for TEST FAULT TIMELINE: a .toml/.json file or inline,
+    /// e.g. "t=1.0 crash 2; t=2.5 disconnect 3; t=4 partition {0,1}|{2,3}; t=8 heal"
+    #[clap(long)]
+    faults: Option<String>,
+
+    /// Drop rate for messages
+    #[clap(long = "drop", short = 'd', default_value = "0.0")]
+    drop_rate: f64,
+
+    /// Duplication rate for messages, a duplicated message is delivered 2 or 3 times
+    #[clap(long = "dupl", default_value = "0.0")]
+    dupl_rate: f64,
+
+    /// Message delays, min..max or a single delay
+    #[clap(long)]
+    delays: Option<String>,
+
+    /// Write every event of the runs to this JSON lines trace file
+    #[clap(long)]
+    trace: Option<String>,
+
+    /// Rerun the tests of a trace file with its arguments and check that they produce the same trace
+    #[clap(long)]
+    replay: Option<String>,
+
+    /// Write a report of the tests to this file, JUnit XML for *.xml and JSON otherwise
+    #[clap(long)]
+    report: Option<String>,
 }
 
 fn main() {
-    utils::init_logger(LevelFilter::Trace);
     env::set_var("PYTHONPATH", "../../dslib/python");
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // a replay runs with the arguments of the recorded run
+    let recording = args.replay.as_deref().map(utils::trace::load);
+    if let Some(recording) = recording.as_ref() {
+        args = Args::parse_from(&recording.args);
+        args.trace = None;
+        args.report = None;
+    }
+    let seeds = utils::fuzz::get_seeds(args.seed, args.seed_count, args.seed_range.as_deref())
+        .or_else(|| args.shrink.then(|| vec![args.seed]));
+    utils::init_logger(if seeds.is_some() || recording.is_some() { LevelFilter::Off } else { LevelFilter::Trace });
+    if let Some(path) = args.trace.as_deref() {
+        utils::trace::start(path);
+    }
+    if let Some(path) = args.report.as_deref() {
+        utils::report::start(path);
+    }
 
-    let node_factory = PyNodeFactory::new(&args.impl_path, "RBNode");
-    let byz_node_factory = PyNodeFactory::new(&args.impl_path, "ByzRBNode");
-    let config = utils::TestConfig {
+    let overrides = args.overrides.as_deref().map(utils::RunOverrides::parse);
+    let faults = args.faults.as_deref().map(utils::faults::FaultTimeline::from_arg);
+    let options = utils::SuiteOptions {
         node_count: args.node_count,
         faulty_count: args.faulty_count,
-        node_factory: &node_factory,
-        byz_node_factory: Some(&byz_node_factory),
         seed: args.seed,
-        check_termination: false,
+        overrides: overrides.as_ref(),
+        faults: faults.as_ref(),
+        network: utils::NetworkConfig {
+            drop_rate: args.drop_rate,
+            dupl_rate: args.dupl_rate,
+            delays: args.delays.as_deref().map(utils::NetworkConfig::parse_delays),
+        },
     };
-
-    let mut tests = TestSuite::new();
-    tests.add("TEST SIMPLE", test_simple, config);
-    tests.add("TEST DISCONNECT AFTER INIT", test_disconnect_after_init, config);
-    tests.add("TEST BYZANTINE", test_byzantine, config);
+    let factories = suite::Factories::new(&args.impl_path);
+    let mut tests = utils::fuzz::SeededTestSuite::new();
+    suite::add_tests(&mut tests, &factories, options);
+    if let Some(path) = args.config.as_deref() {
+        tests.expand(&utils::matrix::SuiteConfig::from_arg(path, seeds.is_some()));
+    }
 
     let test = args.test.as_deref();
-    if test.is_none() {
+    if let Some(recording) = recording {
+        tests.replay(&recording);
+    } else if let Some(seeds) = seeds {
+        tests.run_seeds(&seeds, test, args.shrink);
+    } else if test.is_none() {
         tests.run();
     } else {
         tests.run_test(test.unwrap());
@@ -0,0 +1,242 @@
+use assertables::assume;
+
+use dslib::pynode::{ JsonMessage, PyNodeFactory };
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use crate::utils;
+use crate::utils::messages::{ MessageAccept, ProtocolMessage };
+
+static DELIVERED: &str = "ACCEPT";
+static DUPLICATION_RATE: f64 = 1.0;
+static EXPLORE_DEPTH: usize = 5;
+
+/// Steps the system until it has no events, stopping at the first violation
+/// of the reliable broadcast invariants by `correct_nodes`.
+fn step_monitored(sys: &mut System<JsonMessage>, correct_nodes: &[String], config: &utils::TestConfig) -> TestResult {
+    let monitor = utils::monitor::RbMonitor::new(correct_nodes, config.faulty_count);
+    utils::monitor::RunMonitor::new(Box::new(monitor)).step_until_no_events(sys)
+}
+
+// TESTS -----------------------------------------------------------------------
+
+fn test_simple(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let init_value: u64 = 42;
+
+    let mut init_values = Vec::new();
+    init_values.push(init_value);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes, config)?;
+
+    utils::check_delivery(&mut sys, DELIVERED, &nodes, Some(init_value))
+}
+
+fn test_disconnect_after_init(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+
+    let init_value: u64 = 42;
+
+    let mut init_values = Vec::new();
+    init_values.push(init_value);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    utils::step_before_fault(&mut sys, config, 1.0);
+
+    let mut correct_nodes = Vec::<String>::new();
+    let mut disconnected_nodes = Vec::<String>::new();
+
+    for i in 0..config.faulty_count {
+        let node = format!("{}", i);
+        utils::disconnect_node(&mut sys, config, &node);
+        disconnected_nodes.push(node);
+    }
+    for i in config.faulty_count..config.node_count {
+        let node = format!("{}", i);
+        correct_nodes.push(node);
+    }
+
+    step_monitored(&mut sys, &correct_nodes, config)?;
+
+    assume!(utils::check_not_delivery(&mut sys, &disconnected_nodes).is_ok())?;
+    utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, Some(init_value))
+}
+
+
+fn test_byzantine(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system_with_byz(config);
+    let nodes = sys.get_node_ids();
+
+    let init_value: u64 = 42;
+
+    let mut init_values = Vec::new();
+    init_values.push(init_value);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &utils::get_correct_nodes(config), config)?;
+
+    utils::check_not_delivery(&mut sys, &nodes)
+}
+
+fn test_message_complexity(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let init_value: u64 = 42;
+
+    let init_values = vec![init_value];
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &nodes, config)?;
+
+    utils::check_delivery(&mut sys, DELIVERED, &nodes, Some(init_value))?;
+
+    let counter = utils::get_message_counter(&sys);
+    utils::print_message_counts(&counter);
+
+    // one INIT broadcast, then every node broadcasts ECHO and READY once
+    let n = config.node_count as u64;
+    for (tip, expected) in [("INIT", n), ("ECHO", n * n), ("READY", n * n)] {
+        let cnt = counter.count_type(tip);
+        assume!(cnt <= expected, format!("{} {} messages sent, expected at most {}", cnt, tip, expected))?;
+    }
+    Ok(true)
+}
+
+fn test_byzantine_placement(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system_with_byz(config);
+
+    let init_value: u64 = 42;
+
+    let init_values = vec![init_value];
+    utils::send_init_messages(&mut sys, &init_values);
+
+    step_monitored(&mut sys, &utils::get_correct_nodes(config), config)?;
+
+    let correct_nodes = utils::get_correct_nodes(config);
+    let byz_nodes: Vec<String> = utils::get_byzantine_nodes(config)
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+
+    assume!(utils::check_not_delivery(&mut sys, &byz_nodes).is_ok())?;
+    if byz_nodes.contains(&"0".to_string()) {
+        // equivocating sender: neither value can gather 2f+1 echoes
+        utils::check_not_delivery(&mut sys, &correct_nodes)
+    } else {
+        utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, Some(init_value))
+    }
+}
+
+fn test_fault_timeline(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let init_value: u64 = 42;
+
+    let init_values = vec![init_value];
+    utils::send_init_messages(&mut sys, &init_values);
+
+    let faulty_nodes = utils::run_fault_timeline(&mut sys, config)?;
+    let correct_nodes: Vec<String> = nodes.into_iter().filter(|node| !faulty_nodes.contains(node)).collect();
+
+    step_monitored(&mut sys, &correct_nodes, config)?;
+
+    // a faulty sender may leave every correct node without delivery,
+    // otherwise all of them deliver the same value
+    if faulty_nodes.iter().any(|node| node == "0") {
+        if utils::check_not_delivery(&mut sys, &correct_nodes).is_ok() {
+            return Ok(true);
+        }
+        return utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, None);
+    }
+    utils::check_delivery(&mut sys, DELIVERED, &correct_nodes, Some(init_value))
+}
+
+fn test_explore(config: &utils::TestConfig) -> TestResult {
+    let mut small_config = *config;
+    small_config.node_count = 4;
+    small_config.faulty_count = 1;
+    let bounds = utils::explore::ExploreConfig { depth: EXPLORE_DEPTH, max_round: 0, until_decided: false };
+    let correct_nodes = utils::get_correct_nodes(&small_config);
+    let sender_is_correct = correct_nodes.contains(&"0".to_string());
+
+    let init_value: u64 = 42;
+
+    let init_values = vec![init_value];
+    utils::explore::explore(
+        &small_config,
+        bounds,
+        |sys| utils::send_init_messages(sys, &init_values),
+        |sys| {
+            if sender_is_correct {
+                return utils::check_delivery(sys, DELIVERED, &correct_nodes, Some(init_value));
+            }
+            // correct nodes never accept different values
+            let mut accepted = None;
+            for node in correct_nodes.iter() {
+                let messages = utils::get_local_messages(sys, node);
+                assume!(messages.len() <= 1, format!("Node {}: Wrong number of messages!", node))?;
+                if let Some(msg) = messages.first() {
+                    let value = MessageAccept::decode(msg).map_err(|e| format!("Node {}: {}", node, e))?.value;
+                    let expected = *accepted.get_or_insert(value);
+                    assume!(value == expected, format!("Node {}: accepted {} instead of {}", node, value, expected))?;
+                }
+            }
+            Ok(true)
+        },
+    )
+}
+
+// SUITE -----------------------------------------------------------------------
+
+/// Node classes of the implementation under test.
+pub struct Factories {
+    node: PyNodeFactory,
+    byz_node: PyNodeFactory,
+}
+
+impl Factories {
+    pub fn new(impl_path: &str) -> Self {
+        Self {
+            node: PyNodeFactory::new(impl_path, "RBNode"),
+            byz_node: PyNodeFactory::new(impl_path, "ByzRBNode"),
+        }
+    }
+}
+
+pub fn add_tests<'a>(tests: &mut utils::fuzz::SeededTestSuite<'a>, factories: &'a Factories, options: utils::SuiteOptions<'a>) {
+    let mut config = options.config(&factories.node);
+    config.byz_node_factory = Some(&factories.byz_node);
+    config.byz_placement = utils::ByzPlacement::First(1);
+    tests.add("TEST SIMPLE", test_simple, config);
+    tests.add("TEST DISCONNECT AFTER INIT", test_disconnect_after_init, config);
+    tests.add("TEST MESSAGE COMPLEXITY", test_message_complexity, config);
+    tests.add("TEST BYZANTINE", test_byzantine, config);
+    config.byz_placement = utils::ByzPlacement::First(options.faulty_count);
+    tests.add("TEST BYZANTINE FIRST F", test_byzantine_placement, config);
+    config.byz_placement = utils::ByzPlacement::Last(options.faulty_count);
+    tests.add("TEST BYZANTINE LAST F", test_byzantine_placement, config);
+    config.byz_placement = utils::ByzPlacement::Random(options.faulty_count);
+    tests.add("TEST BYZANTINE RANDOM F", test_byzantine_placement, config);
+
+    // every message is delivered 2 or 3 times, a node must count each sender once
+    config.byz_placement = utils::ByzPlacement::First(1);
+    config.network.dupl_rate = DUPLICATION_RATE;
+    tests.add("TEST DUPLICATION SIMPLE", test_simple, config);
+    tests.add("TEST DUPLICATION MESSAGE COMPLEXITY", test_message_complexity, config);
+    tests.add("TEST DUPLICATION BYZANTINE", test_byzantine, config);
+    config.network.dupl_rate = options.network.dupl_rate;
+
+    config.byz_placement = utils::ByzPlacement::None;
+    tests.add("TEST EXPLORE", test_explore, config);
+    config.byz_placement = utils::ByzPlacement::First(1);
+    tests.add("TEST EXPLORE BYZANTINE", test_explore, config);
+
+    if config.faults.is_some() {
+        tests.add("TEST FAULT TIMELINE", test_fault_timeline, config);
+    }
+}
@@ -1,143 +1,10 @@
-use assertables::assume;
 use clap::Parser;
 use log::LevelFilter;
 use std::env;
-use serde_json::Value;
-
-use dslib::pynode::{ JsonMessage, PyNodeFactory };
-use dslib::test::{ TestResult, TestSuite };
-use dslib::system::System;
 
 #[path = "../../../utils/utils.rs"]
 mod utils;
-
-
-pub fn check_decided_proposals(
-    sys: &mut System<JsonMessage>,
-    nodes: &Vec<String>,
-    mut expected_result: String
-) -> TestResult {
-    for node in nodes.iter() {
-        let mut messages = utils::get_local_messages(&sys, &node);
-
-        if messages.len() == 0 {
-            let res = sys.step_until_local_message(&node);
-            assume!(res.is_ok(), format!("Node {}: No messages returned!", node))?;
-            messages = utils::get_local_messages(&sys, &node);
-        }
-
-        assume!(messages.len() == 1, format!("Node {}: Wrong number of messages!", node))?;
-        assume!(messages[0].tip == "RESULT", format!("Node {}: Wrong message type!", node))?;
-
-        let data: Value = serde_json::from_str(&messages[0].data).unwrap();
-        let proposals = data["valid_proposals"].as_str().unwrap().to_string();
-        if expected_result == "" {
-            expected_result = proposals;
-        } else {
-            assume!(
-                proposals == expected_result,
-                format!("Node {}: returned proposals {} instead of {}", node, proposals, expected_result)
-            )?;
-        }
-    }
-    Ok(true)
-}
-
-// TESTS -----------------------------------------------------------------------
-
-fn test_all_same(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
-
-    let value = 42;
-    let mut init_values = Vec::new();
-    init_values.resize(nodes.len(), value);
-
-    utils::send_init_messages(&mut sys, &init_values);
-
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
-
-    utils::check_consensus(&mut sys, &nodes, Some(value))
-}
-
-fn test_all_diff(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
-
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(10..100));
-    }
-
-    utils::send_init_messages(&mut sys, &init_values);
-
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
-
-    utils::check_consensus(&mut sys, &nodes, None)
-}
-
-fn test_proposals(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
-
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(10..100));
-    }
-
-    utils::send_init_messages(&mut sys, &init_values);
-
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
-
-    let mut expected_proposals = Vec::new();
-    for value in init_values.iter() {
-        expected_proposals.push(value.to_string());
-    }
-    expected_proposals.sort();
-
-    check_decided_proposals(&mut sys, &nodes, expected_proposals.join(";"))
-}
-
-fn test_faulty(config: &utils::TestConfig) -> TestResult {
-    let mut sys = utils::build_system(config);
-    let nodes = sys.get_node_ids();
-
-    let mut init_values = Vec::new();
-    for _ in nodes.iter() {
-        init_values.push(sys.gen_range(10..100));
-    }
-
-    utils::send_init_messages(&mut sys, &init_values);
-    sys.step();
-
-    let mut correct_nodes = Vec::<String>::new();
-    let mut disconnected_nodes = Vec::<String>::new();
-    let mut expected_proposals = Vec::new();
-
-    for i in 0..config.faulty_count {
-        let node = format!("{}", i);
-        sys.crash_node(&node);
-        disconnected_nodes.push(node);
-    }
-    for i in config.faulty_count..config.node_count {
-        let node = format!("{}", i);
-        correct_nodes.push(node);
-        expected_proposals.push(init_values[i as usize].to_string());
-    }
-    expected_proposals.sort();
-
-    if config.check_termination {
-        sys.step_until_no_events();
-    }
-
-    check_decided_proposals(&mut sys, &correct_nodes, expected_proposals.join(";"))
-}
+mod suite;
 
 // MAIN ------------------------------------------------------------------------
 
@@ -167,36 +34,154 @@ struct Args {
     /// Test to run (optional)
     #[clap(long, short)]
     test: Option<String>,
+
+    /// Suite config (TOML) with the (n, f) pairs, seeds and delays to run every test with,
+    /// see utils/matrix.rs
+    #[clap(long)]
+    config: Option<String>,
+
+    /// Run every test with this many seeds, starting from --seed
+    #[clap(long = "seeds", conflicts_with = "seed-range")]
+    seed_count: Option<u64>,
+
+    /// Run every test with the seeds from this range, a..b or a..=b
+    #[clap(long = "seed-range")]
+    seed_range: Option<String>,
+
+    /// Shrink the first failure of every test to a smaller failing scenario
+    #[clap(long)]
+    shrink: bool,
+
+    /// Init values and faults of the tests, as JSON reported by --shrink
+    #[clap(long)]
+    overrides: Option<String>,
+
+    /// Fault timeline for TEST FAULT TIMELINE: a .toml/.json file or inline,
+    /// e.g. "t=1.0 crash 2; t=2.5 disconnect 3; t=4 partition {0,1}|{2,3}; t=8 heal"
+    #[clap(long)]
+    faults: Option<String>,
+
+    /// Drop rate for messages
+    #[clap(long = "drop", short = 'd', default_value = "0.0")]
+    drop_rate: f64,
+
+    /// Duplication rate for messages, a duplicated message is delivered 2 or 3 times
+    #[clap(long = "dupl", default_value = "0.0")]
+    dupl_rate: f64,
+
+    /// Message delays, min..max or a single delay
+    #[clap(long)]
+    delays: Option<String>,
+
+    /// Write every event of the runs to this JSON lines trace file
+    #[clap(long)]
+    trace: Option<String>,
+
+    /// Rerun the tests of a trace file with its arguments and check that they produce the same trace
+    #[clap(long)]
+    replay: Option<String>,
+
+    /// Write a report of the tests to this file, JUnit XML for *.xml and JSON otherwise
+    #[clap(long)]
+    report: Option<String>,
+
+    /// Run the parameter sweep described by this JSON grid file instead of tests
+    #[clap(long)]
+    sweep: Option<String>,
+
+    /// Sweep output file, CSV for *.csv and JSON lines otherwise (stdout by default)
+    #[clap(long = "sweep-output")]
+    sweep_output: Option<String>,
+
+    /// Number of parallel sweep workers (number of CPUs by default)
+    #[clap(long, short)]
+    jobs: Option<usize>,
+
+    /// Re-run the experiment stored in this baseline file and fail on performance regressions
+    #[clap(long)]
+    regression: Option<String>,
+
+    /// Run the --sweep grid and store the results as a baseline file instead of rows
+    #[clap(long = "write-baseline", requires = "sweep")]
+    write_baseline: Option<String>,
+
+    /// Allowed relative increase of rounds and latency in the regression check
+    #[clap(long, default_value = "0.1")]
+    tolerance: f64,
+
+    /// Significance level of the regression check
+    #[clap(long, default_value = "0.01")]
+    alpha: f64,
+
+    /// Internal: run one sweep cell given as JSON
+    #[clap(long = "sweep-worker", hide = true)]
+    sweep_worker: Option<String>,
 }
 
 fn main() {
-    utils::init_logger(LevelFilter::Trace);
     env::set_var("PYTHONPATH", "../../dslib/python");
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // a replay runs with the arguments of the recorded run
+    let recording = args.replay.as_deref().map(utils::trace::load);
+    if let Some(recording) = recording.as_ref() {
+        args = Args::parse_from(&recording.args);
+        args.trace = None;
+        args.report = None;
+    }
 
-    let node_factory = PyNodeFactory::new(&args.impl_path, "DBFT");
-    let mut config = utils::TestConfig {
+    if let Some(cell) = args.sweep_worker.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        utils::sweep::run_worker(cell, &args.impl_path);
+        return;
+    }
+    if let Some(baseline) = args.regression.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        utils::regression::run_and_exit(baseline, &args.impl_path, args.jobs, args.tolerance, args.alpha);
+    }
+    if let Some(grid) = args.sweep.as_deref() {
+        utils::init_logger(LevelFilter::Off);
+        if let Some(baseline) = args.write_baseline.as_deref() {
+            utils::regression::write_baseline_and_exit(grid, baseline, &args.impl_path, args.jobs);
+        }
+        utils::sweep::run_and_exit(grid, &args.impl_path, args.jobs, args.sweep_output.as_deref());
+    }
+    let seeds = utils::fuzz::get_seeds(args.seed, args.seed_count, args.seed_range.as_deref())
+        .or_else(|| args.shrink.then(|| vec![args.seed]));
+    utils::init_logger(if seeds.is_some() || recording.is_some() { LevelFilter::Off } else { LevelFilter::Trace });
+    if let Some(path) = args.trace.as_deref() {
+        utils::trace::start(path);
+    }
+    if let Some(path) = args.report.as_deref() {
+        utils::report::start(path);
+    }
+
+    let overrides = args.overrides.as_deref().map(utils::RunOverrides::parse);
+    let faults = args.faults.as_deref().map(utils::faults::FaultTimeline::from_arg);
+    let options = utils::SuiteOptions {
         node_count: args.node_count,
         faulty_count: args.faulty_count,
-        node_factory: &node_factory,
-        byz_node_factory: None,
         seed: args.seed,
-        check_termination: false,
+        overrides: overrides.as_ref(),
+        faults: faults.as_ref(),
+        network: utils::NetworkConfig {
+            drop_rate: args.drop_rate,
+            dupl_rate: args.dupl_rate,
+            delays: args.delays.as_deref().map(utils::NetworkConfig::parse_delays),
+        },
     };
-
-    let mut tests = TestSuite::new();
-    tests.add("TEST ALL SAME", test_all_same, config);
-    tests.add("TEST ALL DIFF", test_all_diff, config);
-    tests.add("TEST PROPOSALS", test_proposals, config);
-    tests.add("TEST FAULTY", test_faulty, config);
-    config.check_termination = true;
-    tests.add("TEST TERMINATION ALL SAME", test_all_same, config);
-    tests.add("TEST TERMINATION ALL DIFF", test_all_diff, config);
-    tests.add("TEST TERMINATION PROPOSALS", test_proposals, config);
-    tests.add("TEST TERMINATION FAULTY", test_faulty, config);
+    let factories = suite::Factories::new(&args.impl_path);
+    let mut tests = utils::fuzz::SeededTestSuite::new();
+    suite::add_tests(&mut tests, &factories, options);
+    if let Some(path) = args.config.as_deref() {
+        tests.expand(&utils::matrix::SuiteConfig::from_arg(path, seeds.is_some()));
+    }
 
     let test = args.test.as_deref();
-    if test.is_none() {
+    if let Some(recording) = recording {
+        tests.replay(&recording);
+    } else if let Some(seeds) = seeds {
+        tests.run_seeds(&seeds, test, args.shrink);
+    } else if test.is_none() {
         tests.run();
     } else {
         tests.run_test(test.unwrap());
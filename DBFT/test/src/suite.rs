@@ -0,0 +1,269 @@
+use assertables::assume;
+
+use dslib::pynode::{ JsonMessage, PyNodeFactory };
+use dslib::test::TestResult;
+use dslib::system::System;
+
+use crate::utils;
+use crate::utils::messages::{ MessageResult, ProtocolMessage };
+
+
+pub fn check_decided_proposals(
+    sys: &mut System<JsonMessage>,
+    nodes: &Vec<String>,
+    expected_result: String
+) -> TestResult {
+    let result = check_proposals(sys, nodes, expected_result);
+    utils::with_diagnostics(sys, result)
+}
+
+fn check_proposals(
+    sys: &mut System<JsonMessage>,
+    nodes: &Vec<String>,
+    mut expected_result: String
+) -> TestResult {
+    for node in nodes.iter() {
+        let mut messages = utils::get_local_messages(&sys, &node);
+
+        if messages.len() == 0 {
+            let res = sys.step_until_local_message(&node);
+            assume!(res.is_ok(), format!("Node {}: No messages returned!", node))?;
+            messages = utils::get_local_messages(&sys, &node);
+        }
+
+        assume!(messages.len() == 1, format!("Node {}: Wrong number of messages!", node))?;
+        assume!(messages[0].tip == "RESULT", format!("Node {}: Wrong message type!", node))?;
+
+        let result = MessageResult::decode(&messages[0]).map_err(|e| format!("Node {}: {}", node, e))?;
+        let proposals = result.valid_proposals
+            .ok_or_else(|| format!("Node {}: RESULT without valid_proposals: {:?}", node, messages[0]))?;
+        if expected_result == "" {
+            expected_result = proposals;
+        } else {
+            assume!(
+                proposals == expected_result,
+                format!("Node {}: returned proposals {} instead of {}", node, proposals, expected_result)
+            )?;
+        }
+    }
+    Ok(true)
+}
+
+// TESTS -----------------------------------------------------------------------
+
+fn test_all_same(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let value = 42;
+    let mut init_values = Vec::new();
+    init_values.resize(nodes.len(), value);
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        sys.step_until_no_events();
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::MultiValued)
+}
+
+fn test_all_diff(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(10..100));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        sys.step_until_no_events();
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::MultiValued)
+}
+
+fn test_proposals(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(10..100));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        sys.step_until_no_events();
+    }
+
+    let mut expected_proposals = Vec::new();
+    for value in init_values.iter() {
+        expected_proposals.push(value.to_string());
+    }
+    expected_proposals.sort();
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::MultiValued)?;
+    check_decided_proposals(&mut sys, &nodes, expected_proposals.join(";"))
+}
+
+fn test_faulty(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(10..100));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+    sys.step();
+
+    let mut correct_nodes = Vec::<String>::new();
+    let mut disconnected_nodes = Vec::<String>::new();
+    let mut expected_proposals = Vec::new();
+
+    for i in 0..config.faulty_count {
+        let node = format!("{}", i);
+        utils::crash_node(&mut sys, config, &node);
+        disconnected_nodes.push(node);
+    }
+    for i in config.faulty_count..config.node_count {
+        let node = format!("{}", i);
+        correct_nodes.push(node);
+        expected_proposals.push(init_values[i as usize].to_string());
+    }
+    expected_proposals.sort();
+
+    if config.check_termination {
+        sys.step_until_no_events();
+    }
+
+    utils::check_consensus_properties(
+        &mut sys, &correct_nodes, &init_values, utils::Validity::MultiValued
+    )?;
+    check_decided_proposals(&mut sys, &correct_nodes, expected_proposals.join(";"))
+}
+
+fn test_latency(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+    sys.set_delays(1.0, 5.0);
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(10..100));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    if config.check_termination {
+        sys.step_until_no_events();
+    }
+
+    utils::check_consensus_properties(&mut sys, &nodes, &init_values, utils::Validity::MultiValued)?;
+    utils::print_decision_latency(&sys, &nodes);
+    Ok(true)
+}
+
+fn test_fault_timeline(config: &utils::TestConfig) -> TestResult {
+    let mut sys = utils::build_system(config);
+    let nodes = sys.get_node_ids();
+
+    let mut init_values = Vec::new();
+    for _ in nodes.iter() {
+        init_values.push(sys.gen_range(10..100));
+    }
+
+    let init_values = utils::get_init_values(config, init_values);
+    utils::send_init_messages(&mut sys, &init_values);
+
+    let faulty_nodes = utils::run_fault_timeline(&mut sys, config)?;
+    let correct_nodes: Vec<String> = nodes.into_iter().filter(|node| !faulty_nodes.contains(node)).collect();
+
+    if config.check_termination {
+        sys.step_until_no_events();
+    }
+
+    utils::check_consensus_properties(&mut sys, &correct_nodes, &init_values, utils::Validity::MultiValued)
+}
+
+const PARTITION_HEAL_TIME: f64 = 20.0;
+
+fn gen_init_values(sys: &mut System<JsonMessage>) -> Vec<u64> {
+    sys.get_node_ids().iter().map(|_| sys.gen_range(10..100)).collect()
+}
+
+fn test_partition_minority(config: &utils::TestConfig) -> TestResult {
+    let groups = utils::partition::minority_groups(config.node_count, config.faulty_count);
+    utils::partition::run_partition_test(
+        config, &groups, PARTITION_HEAL_TIME, gen_init_values, utils::Validity::MultiValued
+    )
+}
+
+fn test_partition_split(config: &utils::TestConfig) -> TestResult {
+    let groups = utils::partition::split_groups(config.node_count);
+    utils::partition::run_partition_test(
+        config, &groups, PARTITION_HEAL_TIME, gen_init_values, utils::Validity::MultiValued
+    )
+}
+
+fn test_partition_coordinator(config: &utils::TestConfig) -> TestResult {
+    let groups = utils::partition::coordinator_groups(1, config.node_count);
+    utils::partition::run_partition_test(
+        config, &groups, PARTITION_HEAL_TIME, gen_init_values, utils::Validity::MultiValued
+    )
+}
+
+/// Bounds of the coordinator delay adversary in `test_coord_delay`
+const COORD_DELAYS: [f64; 3] = [2.0, 4.0, 8.0];
+/// Round after which a run against the adversary is cut
+const MAX_COORD_DELAY_ROUND: u64 = 50;
+
+fn test_coord_delay(config: &utils::TestConfig) -> TestResult {
+    utils::adversary::run_coord_delay_test(config, &COORD_DELAYS, MAX_COORD_DELAY_ROUND, gen_init_values, utils::Validity::MultiValued)
+}
+
+// SUITE -----------------------------------------------------------------------
+
+/// Node classes of the implementation under test.
+pub struct Factories {
+    node: PyNodeFactory,
+}
+
+impl Factories {
+    pub fn new(impl_path: &str) -> Self {
+        Self { node: PyNodeFactory::new(impl_path, "DBFT") }
+    }
+}
+
+pub fn add_tests<'a>(tests: &mut utils::fuzz::SeededTestSuite<'a>, factories: &'a Factories, options: utils::SuiteOptions<'a>) {
+    let mut config = options.config(&factories.node);
+    tests.add("TEST ALL SAME", test_all_same, config);
+    tests.add("TEST ALL DIFF", test_all_diff, config);
+    tests.add("TEST PROPOSALS", test_proposals, config);
+    tests.add("TEST FAULTY", test_faulty, config);
+    config.check_termination = true;
+    tests.add("TEST TERMINATION ALL SAME", test_all_same, config);
+    tests.add("TEST TERMINATION ALL DIFF", test_all_diff, config);
+    tests.add("TEST TERMINATION PROPOSALS", test_proposals, config);
+    tests.add("TEST TERMINATION FAULTY", test_faulty, config);
+    tests.add("TEST LATENCY", test_latency, config);
+
+    tests.add("TEST PARTITION MINORITY", test_partition_minority, config);
+    tests.add("TEST PARTITION SPLIT", test_partition_split, config);
+    tests.add("TEST PARTITION COORDINATOR", test_partition_coordinator, config);
+    tests.add("TEST COORD DELAY", test_coord_delay, config);
+    if config.faults.is_some() {
+        tests.add("TEST FAULT TIMELINE", test_fault_timeline, config);
+    }
+}
@@ -0,0 +1,260 @@
+use std::fs;
+
+use serde_json::Value;
+
+#[derive(Copy, Clone, Debug, PartialEq, clap::ArgEnum)]
+pub enum Format {
+    Mermaid,
+    Plantuml,
+}
+
+/// Sequence diagram of a run recorded with `--trace` in the test harnesses
+#[derive(clap::Args, Debug)]
+pub struct DiagramArgs {
+    /// Trace file written with --trace
+    trace: String,
+
+    /// Diagram syntax
+    #[clap(long, short, arg_enum, default_value = "mermaid")]
+    format: Format,
+
+    /// Test of the trace (the first one by default)
+    #[clap(long, short)]
+    test: Option<String>,
+
+    /// Run (built system) of the test, from 0
+    #[clap(long, default_value = "0")]
+    run: usize,
+
+    /// Only messages between these nodes, e.g. 0,1,2
+    #[clap(long, use_value_delimiter = true)]
+    nodes: Vec<String>,
+
+    /// Only these message types, e.g. RB_ECHO,RB_READY
+    #[clap(long, use_value_delimiter = true)]
+    types: Vec<String>,
+
+    /// Only messages of this round
+    #[clap(long)]
+    round: Option<u64>,
+
+    /// Only messages of this DBFT instance (`k` of the message or `sender` of RB messages)
+    #[clap(long)]
+    instance: Option<String>,
+
+    /// Show the dropped messages too
+    #[clap(long)]
+    drops: bool,
+
+    /// Output file (stdout by default)
+    #[clap(long, short)]
+    output: Option<String>,
+}
+
+impl DiagramArgs {
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    /// Whether the filters look into the messages, then timers and crashes are hidden.
+    fn filters_messages(&self) -> bool {
+        !self.types.is_empty() || self.round.is_some() || self.instance.is_some()
+    }
+
+    fn shows_node(&self, node: &str) -> bool {
+        self.nodes.is_empty() || self.nodes.iter().any(|n| n == node)
+    }
+
+    fn shows_message(&self, line: &Value) -> bool {
+        let data = &line["data"];
+        let type_matches = self.types.is_empty() || self.types.iter().any(|t| line["type"] == t.as_str());
+        let round_matches = self.round.is_none() || data["round"].as_u64() == self.round;
+        let instance_matches = match &self.instance {
+            None => true,
+            Some(instance) => {
+                let k = if data["k"].is_null() { &data["sender"] } else { &data["k"] };
+                match k {
+                    Value::String(k) => k == instance,
+                    Value::Number(k) => k.to_string() == *instance,
+                    _ => false,
+                }
+            }
+        };
+        type_matches && round_matches && instance_matches
+    }
+}
+
+/// Element of the diagram.
+enum Step {
+    Message { time: f64, from: String, to: String, label: String, dropped: bool },
+    Note { time: f64, node: String, text: String },
+}
+
+fn str_field<'a>(line: &'a Value, field: &str) -> &'a str {
+    line[field].as_str().unwrap_or_default()
+}
+
+fn message_label(line: &Value) -> String {
+    let data = match &line["data"] {
+        Value::Object(fields) if fields.is_empty() => String::new(),
+        data => format!(" {}", data),
+    };
+    format!("{}{}", str_field(line, "type"), data)
+}
+
+/// Lines of the chosen test and run.
+fn select_run(lines: &[Value], args: &DiagramArgs) -> Result<Vec<Value>, String> {
+    let mut test_found = false;
+    let mut in_test = false;
+    let mut run = None;
+    let mut result = Vec::new();
+    for line in lines {
+        match line["event"].as_str() {
+            Some("test") => {
+                in_test = !test_found && args.test.as_deref().is_none_or(|test| line["test"] == test);
+                test_found |= in_test;
+                run = None;
+            }
+            Some("run") => run = line["run"].as_u64(),
+            Some("trace") | Some("result") => {}
+            _ if in_test && run == Some(args.run as u64) => result.push(line.clone()),
+            _ => {}
+        }
+    }
+    if !test_found {
+        return Err(format!("No test {} in the trace", args.test.as_deref().unwrap_or_default()));
+    }
+    if result.is_empty() {
+        return Err(format!("No events in run {} of the test", args.run));
+    }
+    Ok(result)
+}
+
+fn collect_steps(events: &[Value], args: &DiagramArgs) -> (Vec<String>, Vec<Step>) {
+    let mut nodes: Vec<String> = Vec::new();
+    let mut steps = Vec::new();
+    let mut add_node = |node: &str| {
+        if !nodes.iter().any(|n| n == node) {
+            nodes.push(node.to_string());
+        }
+    };
+    for line in events {
+        let time = line["time"].as_f64().unwrap_or_default();
+        let event = str_field(line, "event");
+        match event {
+            "deliver" | "drop" => {
+                let (from, to) = (str_field(line, "src"), str_field(line, "dest"));
+                if (event == "drop" && !args.drops) || !args.shows_node(from) || !args.shows_node(to) || !args.shows_message(line) {
+                    continue;
+                }
+                add_node(from);
+                add_node(to);
+                steps.push(Step::Message {
+                    time,
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    label: message_label(line),
+                    dropped: event == "drop",
+                });
+            }
+            "local_send" => {
+                let node = str_field(line, "node");
+                if !args.shows_node(node) || !args.shows_message(line) {
+                    continue;
+                }
+                add_node(node);
+                steps.push(Step::Note { time, node: node.to_string(), text: format!("output {}", message_label(line)) });
+            }
+            "timer_fire" | "crash" | "disconnect" | "connect" => {
+                let node = str_field(line, "node");
+                if args.filters_messages() || !args.shows_node(node) {
+                    continue;
+                }
+                add_node(node);
+                let text = match event {
+                    "timer_fire" => format!("timer {}", str_field(line, "timer")),
+                    _ => event.to_string(),
+                };
+                steps.push(Step::Note { time, node: node.to_string(), text });
+            }
+            _ => {}
+        }
+    }
+    // nodes are numbered, keep them in their order
+    nodes.sort_by_key(|node| (node.parse::<u64>().unwrap_or(u64::MAX), node.clone()));
+    (nodes, steps)
+}
+
+fn participant(node: &str) -> String {
+    format!("n{}", node.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect::<String>())
+}
+
+fn mermaid(nodes: &[String], steps: &[Step]) -> String {
+    // ';' and '#' have a meaning in Mermaid, they are written as entity codes
+    let escape = |text: &str| -> String {
+        text.chars()
+            .map(|c| match c {
+                '#' => "#35;".to_string(),
+                ';' => "#59;".to_string(),
+                c => c.to_string(),
+            })
+            .collect()
+    };
+    let mut result = String::from("sequenceDiagram\n");
+    for node in nodes {
+        result += &format!("    participant {} as {}\n", participant(node), node);
+    }
+    for step in steps {
+        match step {
+            Step::Message { time, from, to, label, dropped } => {
+                let arrow = if *dropped { "-x" } else { "->>" };
+                result += &format!("    {}{}{}: {:.3} {}\n", participant(from), arrow, participant(to), time, escape(label));
+            }
+            Step::Note { time, node, text } => {
+                result += &format!("    Note over {}: {:.3} {}\n", participant(node), time, escape(text));
+            }
+        }
+    }
+    result
+}
+
+fn plantuml(nodes: &[String], steps: &[Step]) -> String {
+    let mut result = String::from("@startuml\n");
+    for node in nodes {
+        result += &format!("participant \"{}\" as {}\n", node, participant(node));
+    }
+    for step in steps {
+        match step {
+            Step::Message { time, from, to, label, dropped } => {
+                let arrow = if *dropped { "->x" } else { "->" };
+                result += &format!("{} {} {} : {:.3} {}\n", participant(from), arrow, participant(to), time, label);
+            }
+            Step::Note { time, node, text } => {
+                result += &format!("note over {} : {:.3} {}\n", participant(node), time, text);
+            }
+        }
+    }
+    result += "@enduml\n";
+    result
+}
+
+/// Renders the diagram of the trace chosen by `args`.
+pub fn render(args: &DiagramArgs) -> Result<String, String> {
+    let text = fs::read_to_string(&args.trace).map_err(|e| format!("Can't read {}: {}", args.trace, e))?;
+    let lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| serde_json::from_str(line).map_err(|e| format!("{}:{}: {}", args.trace, idx + 1, e)))
+        .collect::<Result<Vec<Value>, String>>()?;
+
+    let events = select_run(&lines, args)?;
+    let (nodes, steps) = collect_steps(&events, args);
+    if steps.is_empty() {
+        return Err("No events match the filters".to_string());
+    }
+    Ok(match args.format {
+        Format::Mermaid => mermaid(&nodes, &steps),
+        Format::Plantuml => plantuml(&nodes, &steps),
+    })
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::Deserialize;
+
+/// Runs are grouped by protocol, (n, f) and input distribution.
+/// `stat:` logs don't name the protocol, so it is empty for them.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GroupKey {
+    pub protocol: String,
+    pub node_count: u32,
+    pub faulty_count: u32,
+    pub input: String,
+}
+
+impl std::fmt::Display for GroupKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.protocol.is_empty() {
+            write!(f, "{} ", self.protocol)?;
+        }
+        write!(f, "n={} f={} {}", self.node_count, self.faulty_count, self.input)
+    }
+}
+
+/// Measurements of a single run, only finite values are kept.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub key: GroupKey,
+    /// Average round in which the nodes decided
+    pub rounds: Option<f64>,
+    /// Median decision latency of the nodes
+    pub latency: Option<f64>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, clap::ArgEnum)]
+pub enum Metric {
+    Rounds,
+    Latency,
+}
+
+impl Metric {
+    pub fn of(&self, sample: &Sample) -> Option<f64> {
+        match self {
+            Metric::Rounds => sample.rounds,
+            Metric::Latency => sample.latency,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Metric::Rounds => "rounds to decide",
+            Metric::Latency => "decision latency",
+        }
+    }
+}
+
+/// Row of the sweep output, only the fields used by the analyzer.
+#[derive(Deserialize)]
+struct SweepRow {
+    protocol: String,
+    node_count: u32,
+    faulty_count: u32,
+    input: String,
+    avg_round: Option<f64>,
+    latency_median: Option<f64>,
+}
+
+impl SweepRow {
+    fn into_sample(self) -> Sample {
+        Sample {
+            key: GroupKey {
+                protocol: self.protocol,
+                node_count: self.node_count,
+                faulty_count: self.faulty_count,
+                input: self.input,
+            },
+            rounds: finite(self.avg_round),
+            latency: finite(self.latency_median),
+        }
+    }
+}
+
+/// Drops NaN and infinite measurements, they can't be summarized.
+fn finite(value: Option<f64>) -> Option<f64> {
+    value.filter(|value| value.is_finite())
+}
+
+/// Splits a CSV line, honoring double-quoted fields.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn parse_csv_row(header: &[String], line: &str) -> Result<SweepRow, String> {
+    let mut row = serde_json::Map::new();
+    for (name, value) in header.iter().zip(split_csv(line)) {
+        let value = match name.as_str() {
+            "protocol" | "input" | "error" => serde_json::Value::String(value),
+            _ if value.is_empty() => serde_json::Value::Null,
+            _ => serde_json::from_str(&value).map_err(|e| format!("{}: {}", name, e))?,
+        };
+        row.insert(name.clone(), value);
+    }
+    serde_json::from_value(serde_json::Value::Object(row)).map_err(|e| e.to_string())
+}
+
+/// Parses "<seed> <n> <f> ... <percentage_of_ones>" written by
+/// `check_consensus_and_print_statistics`, returning the key, the seed and
+/// the fields in between.
+fn parse_stat_fields(fields: &str) -> Option<(GroupKey, u64, Vec<f64>)> {
+    let tokens: Vec<&str> = fields.split_whitespace().collect();
+    if tokens.len() < 4 {
+        return None;
+    }
+    let seed = tokens[0].parse().ok()?;
+    let key = GroupKey {
+        protocol: String::new(),
+        node_count: tokens[1].parse().ok()?,
+        faulty_count: tokens[2].parse().ok()?,
+        input: format!("ones{}", tokens[tokens.len() - 1].parse::<u64>().ok()?),
+    };
+    let values = tokens[3..tokens.len() - 1]
+        .iter()
+        .map(|t| t.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    Some((key, seed, values))
+}
+
+/// Reads `stat:` logs (with optional `latency:` lines) and sweep output in
+/// CSV or JSON lines format. The format is detected line by line.
+pub fn read_samples(path: &str) -> Result<Vec<Sample>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+
+    let mut samples = Vec::new();
+    let mut stat_samples = BTreeMap::<(GroupKey, u64), Sample>::new();
+    let mut csv_header: Option<Vec<String>> = None;
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let error = |e: String| format!("{}:{}: {}", path, line_idx + 1, e);
+
+        if let Some(fields) = line.strip_prefix("stat:") {
+            if let Some((key, seed, values)) = parse_stat_fields(fields) {
+                let sample = stat_samples.entry((key.clone(), seed)).or_insert(Sample {
+                    key, rounds: None, latency: None
+                });
+                sample.rounds = finite(values.first().copied());
+            }
+        } else if let Some(fields) = line.strip_prefix("latency:") {
+            // "latency: <seed> <n> <f> <min> <median> <max> <p99> <percentage>"
+            if let Some((key, seed, values)) = parse_stat_fields(fields) {
+                let sample = stat_samples.entry((key.clone(), seed)).or_insert(Sample {
+                    key, rounds: None, latency: None
+                });
+                sample.latency = finite(values.get(1).copied());
+            }
+        } else if line.starts_with('{') {
+            let row: SweepRow = serde_json::from_str(line).map_err(|e| error(e.to_string()))?;
+            samples.push(row.into_sample());
+        } else if line.starts_with("protocol,") {
+            csv_header = Some(split_csv(line));
+        } else if let Some(header) = csv_header.as_ref() {
+            if !line.is_empty() {
+                samples.push(parse_csv_row(header, line).map_err(error)?.into_sample());
+            }
+        }
+    }
+
+    samples.extend(stat_samples.into_values());
+    Ok(samples)
+}
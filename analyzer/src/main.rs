@@ -0,0 +1,105 @@
+use clap::{ Parser, Subcommand };
+use std::collections::BTreeMap;
+use std::fs;
+use std::process;
+
+mod diagram;
+mod input;
+mod render;
+mod stats;
+
+use input::{ GroupKey, Metric };
+
+#[derive(Parser, Debug)]
+#[clap(about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+/// Statistics of `stat:` logs and sweep output (CSV or JSON lines)
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Input files, e.g. BinaryByzantineConsensus/PsyncStatistics.log
+    #[clap(required = true)]
+    inputs: Vec<String>,
+
+    /// Metric to analyze
+    #[clap(long, short, arg_enum, default_value = "rounds")]
+    metric: Metric,
+
+    /// Width of the histogram bins
+    #[clap(long = "bin-width", short, default_value = "0.25")]
+    bin_width: f64,
+
+    /// Markdown report file (stdout by default)
+    #[clap(long)]
+    markdown: Option<String>,
+
+    /// SVG chart file with a histogram per group
+    #[clap(long)]
+    svg: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    Diagram(diagram::DiagramArgs),
+}
+
+fn write_file(path: &str, content: &str) {
+    if let Err(e) = fs::write(path, content) {
+        eprintln!("Can't write {}: {}", path, e);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Some(Command::Diagram(diagram_args)) = args.command.as_ref() {
+        let diagram = diagram::render(diagram_args).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        match diagram_args.output() {
+            Some(path) => write_file(path, &diagram),
+            None => print!("{}", diagram),
+        }
+        return;
+    }
+    if !(args.bin_width > 0.0 && args.bin_width.is_finite()) {
+        eprintln!("Bin width must be positive");
+        process::exit(1);
+    }
+
+    let mut values = BTreeMap::<GroupKey, Vec<f64>>::new();
+    for path in args.inputs.iter() {
+        let samples = input::read_samples(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        for sample in samples {
+            if let Some(value) = args.metric.of(&sample) {
+                values.entry(sample.key).or_default().push(value);
+            }
+        }
+    }
+    if values.is_empty() {
+        eprintln!("No {} found in the input", args.metric.title());
+        process::exit(1);
+    }
+
+    let groups: Vec<render::Group> = values
+        .into_iter()
+        .map(|(key, values)| render::Group {
+            key,
+            summary: stats::Summary::from_values(&values).unwrap(),
+            histogram: stats::histogram(&values, args.bin_width),
+        })
+        .collect();
+
+    let markdown = render::markdown(&groups, args.metric);
+    match args.markdown.as_ref() {
+        Some(path) => write_file(path, &markdown),
+        None => print!("{}", markdown),
+    }
+    if let Some(path) = args.svg.as_ref() {
+        write_file(path, &render::svg(&groups, args.metric));
+    }
+}
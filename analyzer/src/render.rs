@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+use crate::input::{ GroupKey, Metric };
+use crate::stats::{ Bin, Summary };
+
+pub struct Group {
+    pub key: GroupKey,
+    pub summary: Summary,
+    pub histogram: Vec<Bin>,
+}
+
+const BAR_WIDTH: usize = 40;
+
+fn bin_label(bin: &Bin) -> String {
+    format!("[{:.2}, {:.2})", bin.start, bin.end)
+}
+
+pub fn markdown(groups: &[Group], metric: Metric) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Statistics of {}", metric.title()).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "| protocol | n | f | input | runs | mean | median | variance | p95 | p99 | min | max |").unwrap();
+    writeln!(out, "|---|---:|---:|---|---:|---:|---:|---:|---:|---:|---:|---:|").unwrap();
+    for group in groups {
+        let key = &group.key;
+        let s = &group.summary;
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} |",
+            if key.protocol.is_empty() { "-" } else { &key.protocol },
+            key.node_count, key.faulty_count, key.input,
+            s.count, s.mean, s.median, s.variance, s.p95, s.p99, s.min, s.max
+        ).unwrap();
+    }
+
+    for group in groups {
+        writeln!(out).unwrap();
+        writeln!(out, "## {}", group.key).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "| {} | runs | |", metric.title()).unwrap();
+        writeln!(out, "|---|---:|---|").unwrap();
+        let max_count = group.histogram.iter().map(|b| b.count).max().unwrap_or(1);
+        for bin in &group.histogram {
+            let bar = (bin.count * BAR_WIDTH).div_ceil(max_count);
+            writeln!(out, "| {} | {} | {} |", bin_label(bin), bin.count, "#".repeat(bar)).unwrap();
+        }
+    }
+    out
+}
+
+const SVG_WIDTH: f64 = 720.0;
+const PANEL_HEIGHT: f64 = 240.0;
+const MARGIN_LEFT: f64 = 50.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Renders one histogram panel per group, stacked vertically.
+pub fn svg(groups: &[Group], metric: Metric) -> String {
+    let height = PANEL_HEIGHT * groups.len().max(1) as f64;
+    let plot_width = SVG_WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = PANEL_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="11">"#,
+        w = SVG_WIDTH, h = height
+    ).unwrap();
+    writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+    for (idx, group) in groups.iter().enumerate() {
+        let top = idx as f64 * PANEL_HEIGHT + MARGIN_TOP;
+        let bottom = top + plot_height;
+        let s = &group.summary;
+        let title = format!(
+            "{}: {} runs, mean {:.3}, median {:.3}, p99 {:.3}",
+            group.key, s.count, s.mean, s.median, s.p99
+        );
+        writeln!(out, r#"<text x="{}" y="{}" font-size="13">{}</text>"#, MARGIN_LEFT, top - 15.0, escape(&title)).unwrap();
+        writeln!(
+            out,
+            r#"<line x1="{x}" y1="{top}" x2="{x}" y2="{bottom}" stroke="black"/><line x1="{x}" y1="{bottom}" x2="{x2}" y2="{bottom}" stroke="black"/>"#,
+            x = MARGIN_LEFT, x2 = MARGIN_LEFT + plot_width, top = top, bottom = bottom
+        ).unwrap();
+
+        let max_count = group.histogram.iter().map(|b| b.count).max().unwrap_or(1);
+        writeln!(out, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, MARGIN_LEFT - 5.0, top + 4.0, max_count).unwrap();
+        writeln!(out, r#"<text x="{}" y="{}" text-anchor="end">0</text>"#, MARGIN_LEFT - 5.0, bottom).unwrap();
+
+        let slot = plot_width / group.histogram.len().max(1) as f64;
+        let label_every = (group.histogram.len() / 10).max(1);
+        for (bin_idx, bin) in group.histogram.iter().enumerate() {
+            let x = MARGIN_LEFT + bin_idx as f64 * slot;
+            let bar_height = plot_height * bin.count as f64 / max_count as f64;
+            writeln!(
+                out,
+                r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#4878a8"><title>{} {}: {} runs</title></rect>"##,
+                x + 1.0, bottom - bar_height, (slot - 2.0).max(1.0), bar_height,
+                metric.title(), bin_label(bin), bin.count
+            ).unwrap();
+            if bin_idx % label_every == 0 {
+                writeln!(
+                    out,
+                    r#"<text x="{:.2}" y="{}" text-anchor="middle">{:.2}</text>"#,
+                    x + slot / 2.0, bottom + 15.0, bin.start
+                ).unwrap();
+            }
+        }
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            MARGIN_LEFT + plot_width / 2.0, bottom + 32.0, metric.title()
+        ).unwrap();
+    }
+    writeln!(out, "</svg>").unwrap();
+    out
+}
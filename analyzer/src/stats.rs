@@ -0,0 +1,86 @@
+/// Descriptive statistics of one group of runs.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample (n - 1) variance, zero for a single run
+    pub variance: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl Summary {
+    pub fn from_values(values: &[f64]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let median = if count % 2 == 1 {
+            sorted[count / 2]
+        } else {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        };
+        let variance = if count > 1 {
+            sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64
+        } else {
+            0.0
+        };
+
+        Some(Summary {
+            count,
+            mean,
+            median,
+            variance,
+            min: sorted[0],
+            max: sorted[count - 1],
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        })
+    }
+}
+
+/// Most bins in a histogram, wider bins are used for a larger range of values.
+pub const MAX_BINS: usize = 1000;
+
+/// Bin of a histogram covering [start, end).
+#[derive(Clone, Debug)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// Full histogram from the smallest to the largest value, empty bins included.
+/// Values must be finite, see `input::read_samples`.
+pub fn histogram(values: &[f64], bin_width: f64) -> Vec<Bin> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let bin_width = bin_width.max((max - min) / (MAX_BINS - 2) as f64);
+
+    let first = (min / bin_width).floor() as i64;
+    let last = (max / bin_width).floor() as i64;
+    let mut bins: Vec<Bin> = (first..=last)
+        .map(|idx| Bin { start: idx as f64 * bin_width, end: (idx + 1) as f64 * bin_width, count: 0 })
+        .collect();
+    for value in values {
+        let idx = (value / bin_width).floor() as i64 - first;
+        bins[idx as usize].count += 1;
+    }
+    bins
+}
@@ -28,6 +28,10 @@ class Message:
 
 
 class Context(object):
+    # Timer actions of all nodes as (name, delay), delay -1 for cancel.
+    # Set to a list by the test harness when it records a trace.
+    timer_log = None
+
     def __init__(self, time: float):
         self._time = time
         self._sent_messages: List[Tuple[str, str, str]] = list()
@@ -50,11 +54,15 @@ class Context(object):
         if delay < 0:
             raise ValueError('delay argument has to be non-negative')
         self._timer_actions.append((timer_name, delay))
+        if Context.timer_log is not None:
+            Context.timer_log.append((timer_name, delay))
     
     def cancel_timer(self, timer_name: str):
         if not isinstance(timer_name, str):
             raise TypeError('timer_name argument has to be str, not {}'.format(type(timer_name)))
         self._timer_actions.append((timer_name, -1))
+        if Context.timer_log is not None:
+            Context.timer_log.append((timer_name, -1))
 
     def time(self) -> float:
         return self._time
@@ -0,0 +1,81 @@
+use assertables::assume;
+use std::cell::RefCell;
+use std::rc::Rc;
+use sugars::{ rc, refcell };
+
+use dslib::node::Node;
+use dslib::pynode::{ JsonMessage, PyNodeFactory };
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use crate::utils;
+
+// UTILS -----------------------------------------------------------------------
+
+/// Node classes of the implementation under test. As a `NodeFactory` it
+/// builds the client or the server, depending on the node id.
+pub struct Factories {
+    server_f: PyNodeFactory,
+    client_f: PyNodeFactory,
+}
+
+impl Factories {
+    pub fn new(impl_path: &str) -> Self {
+        Self {
+            server_f: PyNodeFactory::new(impl_path, "Server"),
+            client_f: PyNodeFactory::new(impl_path, "Client"),
+        }
+    }
+}
+
+impl utils::NodeFactory for Factories {
+    fn build_node(
+        &self,
+        node_id: &str,
+        _node_ids: &[String],
+        _faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+        if node_id == "client" {
+            rc!(refcell!(self.client_f.build("client", ("client", "server"), seed)))
+        } else {
+            rc!(refcell!(self.server_f.build(node_id, (node_id,), seed)))
+        }
+    }
+}
+
+fn build_system(config: &utils::TestConfig) -> System<JsonMessage> {
+    let mut sys = System::with_seed(config.seed);
+    for node_id in ["client", "server"] {
+        sys.add_node(config.node_factory.build_node(node_id, &[], 0, config.seed));
+    }
+    return sys;
+}
+
+// TESTS -----------------------------------------------------------------------
+
+fn test_simple(config: &utils::TestConfig) -> TestResult {
+    let mut sys = build_system(config);
+    sys.set_drop_rate(config.network.drop_rate);
+
+    let req = JsonMessage::new("REQUEST", r#"{"ans": "?"}"#);
+    sys.send_local(req, "client");
+    sys.step_until_no_events();
+
+    let messages = utils::get_local_messages(&sys, "client");
+    assume!(messages.len() > 0, "No messages returned by client!")?;
+    assume!(messages.len() == 1, "Wrong number of messages!")?;
+    assume!(messages[0].tip == "RESPONSE", "Wrong message type!")?;
+    assume!(messages[0].data == r#"{"ans": "42"}"#, "Wrong message data!")?;
+    Ok(true)
+}
+
+// SUITE -----------------------------------------------------------------------
+
+pub fn add_tests<'a>(tests: &mut utils::fuzz::SeededTestSuite<'a>, factories: &'a Factories, options: utils::SuiteOptions<'a>) {
+    // a single client and a single server, whatever the options say
+    let mut config = options.config(factories);
+    config.node_count = 2;
+    config.faulty_count = 0;
+    tests.add("TEST SIMPLE", test_simple, config);
+}
@@ -0,0 +1,202 @@
+use clap::Parser;
+use log::LevelFilter;
+use std::env;
+use std::path::Path;
+
+#[path = "../../utils/utils.rs"]
+mod utils;
+
+#[path = "../../ByzantineReliableBroadcast/test/src/suite.rs"]
+mod rb;
+#[path = "../../BinaryValueBroadcast/test/src/suite.rs"]
+mod bvb;
+#[path = "../../BinaryByzantineConsensus/test/src/suite.rs"]
+mod bbc;
+#[path = "../../DBFT/test/src/suite.rs"]
+mod dbft;
+#[path = "../../Ben-Or/test/src/suite.rs"]
+mod ben_or;
+#[path = "../../example/test/src/suite.rs"]
+mod example;
+
+/// Repository root of the build, the default of --root
+const ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
+/// Protocol names accepted by --protocol, in the order their suites run
+const PROTOCOLS: [&str; 6] = ["rb", "bvb", "bbc", "dbft", "ben-or", "example"];
+
+// MAIN ------------------------------------------------------------------------
+
+#[derive(Parser, Debug)]
+#[clap(about = "Runs the test suites of all protocols", long_about = None)]
+struct Args {
+    /// Repository root with the protocol implementations and dslib
+    #[clap(long, default_value = ROOT)]
+    root: String,
+
+    /// Protocol to test, all by default (can be repeated)
+    #[clap(long, short, possible_values = PROTOCOLS, multiple_occurrences = true)]
+    protocol: Vec<String>,
+
+    /// Tests to run, a glob (* and ?) matched against "<protocol>: <test>" or the test name
+    #[clap(long, short)]
+    test: Option<String>,
+
+    /// Suite config (TOML) with the (n, f) pairs, seeds and delays to run every test with,
+    /// see utils/matrix.rs
+    #[clap(long)]
+    config: Option<String>,
+
+    /// Print the names of the selected tests instead of running them
+    #[clap(long)]
+    list: bool,
+
+    /// Random seed used in tests
+    #[clap(long, short, default_value = "42")]
+    seed: u64,
+
+    /// Nodes count (4 by default, 6 for Ben-Or)
+    #[clap(long = "nodes", short = 'n')]
+    node_count: Option<u32>,
+
+    /// Number of faulty nodes
+    #[clap(long = "faulty_count", short = 'f', default_value = "1")]
+    faulty_count: u32,
+
+    /// Run every test with this many seeds, starting from --seed
+    #[clap(long = "seeds", conflicts_with = "seed-range")]
+    seed_count: Option<u64>,
+
+    /// Run every test with the seeds from this range, a..b or a..=b
+    #[clap(long = "seed-range")]
+    seed_range: Option<String>,
+
+    /// Shrink the first failure of every test to a smaller failing scenario
+    #[clap(long)]
+    shrink: bool,
+
+    /// Init values and faults of the tests, as JSON reported by --shrink
+    #[clap(long)]
+    overrides: Option<String>,
+
+    /// Fault timeline for the FAULT TIMELINE tests: a .toml/.json file or inline,
+    /// e.g. "t=1.0 crash 2; t=2.5 disconnect 3; t=4 partition {0,1}|{2,3}; t=8 heal"
+    #[clap(long)]
+    faults: Option<String>,
+
+    /// Drop rate for messages
+    #[clap(long = "drop", short = 'd', default_value = "0.0")]
+    drop_rate: f64,
+
+    /// Duplication rate for messages, a duplicated message is delivered 2 or 3 times
+    #[clap(long = "dupl", default_value = "0.0")]
+    dupl_rate: f64,
+
+    /// Message delays, min..max or a single delay
+    #[clap(long)]
+    delays: Option<String>,
+
+    /// Write every event of the runs to this JSON lines trace file
+    #[clap(long)]
+    trace: Option<String>,
+
+    /// Rerun the tests of a trace file with its arguments and check that they produce the same trace
+    #[clap(long)]
+    replay: Option<String>,
+
+    /// Write a report of the tests to this file, JUnit XML for *.xml and JSON otherwise
+    #[clap(long)]
+    report: Option<String>,
+}
+
+/// Implementation of the protocol in `dir` of the repository.
+fn impl_path(root: &Path, dir: &str) -> String {
+    root.join(dir).join("main.py").to_string_lossy().into_owned()
+}
+
+fn main() {
+    let mut args = Args::parse();
+    // a replay runs with the arguments of the recorded run
+    let recording = args.replay.as_deref().map(utils::trace::load);
+    if let Some(recording) = recording.as_ref() {
+        args = Args::parse_from(&recording.args);
+        args.trace = None;
+        args.report = None;
+    }
+    let root = Path::new(&args.root);
+    if !root.join("dslib").is_dir() {
+        eprintln!("{} is not the repository root, see --root", args.root);
+        std::process::exit(2);
+    }
+    env::set_var("PYTHONPATH", root.join("dslib").join("python"));
+
+    let seeds = utils::fuzz::get_seeds(args.seed, args.seed_count, args.seed_range.as_deref())
+        .or_else(|| args.shrink.then(|| vec![args.seed]));
+    let quiet = args.list || seeds.is_some() || recording.is_some();
+    utils::init_logger(if quiet { LevelFilter::Off } else { LevelFilter::Trace });
+    if let Some(path) = args.trace.as_deref() {
+        utils::trace::start(path);
+    }
+    if let Some(path) = args.report.as_deref() {
+        utils::report::start(path);
+    }
+
+    let overrides = args.overrides.as_deref().map(utils::RunOverrides::parse);
+    let faults = args.faults.as_deref().map(utils::faults::FaultTimeline::from_arg);
+    let options = |default_node_count: u32| utils::SuiteOptions {
+        node_count: args.node_count.unwrap_or(default_node_count),
+        faulty_count: args.faulty_count,
+        seed: args.seed,
+        overrides: overrides.as_ref(),
+        faults: faults.as_ref(),
+        network: utils::NetworkConfig {
+            drop_rate: args.drop_rate,
+            dupl_rate: args.dupl_rate,
+            delays: args.delays.as_deref().map(utils::NetworkConfig::parse_delays),
+        },
+    };
+
+    let rb_factories = rb::Factories::new(&impl_path(root, "ByzantineReliableBroadcast"));
+    let bvb_factories = bvb::Factories::new(&impl_path(root, "BinaryValueBroadcast"));
+    let bbc_factories = bbc::Factories::new(&impl_path(root, "BinaryByzantineConsensus"));
+    let bbc_mixed = bbc_factories.mixed();
+    let dbft_factories = dbft::Factories::new(&impl_path(root, "DBFT"));
+    let ben_or_factories = ben_or::Factories::new(&impl_path(root, "Ben-Or"));
+    let example_factories = example::Factories::new(&impl_path(root, "example"));
+
+    let mut tests = utils::fuzz::SeededTestSuite::new();
+    tests.add_group("rb", |suite| rb::add_tests(suite, &rb_factories, options(4)));
+    tests.add_group("bvb", |suite| bvb::add_tests(suite, &bvb_factories, options(4)));
+    tests.add_group("bbc", |suite| bbc::add_tests(suite, &bbc_factories, &bbc_mixed, options(4)));
+    tests.add_group("dbft", |suite| dbft::add_tests(suite, &dbft_factories, options(4)));
+    tests.add_group("ben-or", |suite| ben_or::add_tests(suite, &ben_or_factories, options(6)));
+    tests.add_group("example", |suite| example::add_tests(suite, &example_factories, options(2)));
+
+    if let Some(path) = args.config.as_deref() {
+        tests.expand(&utils::matrix::SuiteConfig::from_arg(path, seeds.is_some()));
+    }
+
+    if let Some(recording) = recording {
+        tests.replay(&recording);
+    }
+    if !args.protocol.is_empty() {
+        tests.retain_groups(|group| args.protocol.iter().any(|protocol| protocol == group));
+    }
+    if let Some(pattern) = args.test.as_deref() {
+        tests.retain_matching(pattern);
+    }
+    if tests.is_empty() {
+        eprintln!("No test matches {}", args.test.as_deref().unwrap_or("*"));
+        std::process::exit(2);
+    }
+
+    if args.list {
+        for name in tests.names() {
+            println!("{}", name);
+        }
+    } else if let Some(seeds) = seeds {
+        tests.run_seeds(&seeds, None, args.shrink);
+    } else {
+        tests.run();
+    }
+}
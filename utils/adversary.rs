@@ -0,0 +1,229 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use sugars::{ rc, refcell };
+
+use dslib::node::{ Context, Node };
+use dslib::pynode::JsonMessage;
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use super::messages::{ MessageCoordValue, MessageFields, ProtocolMessage };
+use super::{ NodeFactory, TestConfig, Validity };
+
+/// Timers of `CoordDelayNode` start with this prefix, the inner node never sees them.
+const RELEASE_TIMER: &str = "COORD-DELAY";
+/// Coordinator timers of PsyncBBC and DBFT, "COORD-TIMER-NODE-<id>" with
+/// "|k=<instance>" in DBFT.
+const COORD_TIMER: &str = "COORD-TIMER";
+
+/// Protocol instance of a COORD_VALUE message or a coordinator timer:
+/// "k=<instance>" in DBFT, empty in PsyncBBC.
+fn message_instance(coord_value: &MessageCoordValue) -> String {
+    coord_value.k.map_or_else(String::new, |k| format!("k={}", k))
+}
+
+fn timer_instance(timer: &str) -> String {
+    timer.split_once('|').map_or_else(String::new, |(_, instance)| instance.to_string())
+}
+
+/// What the adversary achieved, shared by the nodes of a run.
+#[derive(Clone, Debug, Default)]
+pub struct CoordDelayStats {
+    /// COORD_VALUE messages delivered right after the receiver's timer fired
+    pub forced: u64,
+    /// First round in which a COORD_VALUE reached the receiver before its
+    /// timer despite the delay, by (node, instance)
+    pub overcome: BTreeMap<(String, String), u64>,
+    /// Highest round of the messages received by the nodes
+    pub last_round: u64,
+}
+
+impl CoordDelayStats {
+    /// Round by which the timeouts outgrew the delay at every node and
+    /// instance, `None` if they never did.
+    pub fn overcome_round(&self) -> Option<u64> {
+        self.overcome.values().max().copied()
+    }
+}
+
+struct HeldValue {
+    msg: JsonMessage,
+    from: String,
+    instance: String,
+    round: u64,
+}
+
+/// Wraps a node and delays the COORD_VALUE messages it receives from other
+/// nodes until just after its coordinator timer of that round fires, but by
+/// no more than `max_delay`. Once the timeouts outgrow `max_delay`, the
+/// values arrive in time again.
+pub struct CoordDelayNode {
+    id: String,
+    inner: Rc<RefCell<dyn Node<JsonMessage>>>,
+    max_delay: f64,
+    stats: Rc<RefCell<CoordDelayStats>>,
+    /// Fired coordinator timers by instance, the round the node is in
+    fired: BTreeMap<String, u64>,
+    /// Held messages by the number in the release timer name
+    held: BTreeMap<u64, HeldValue>,
+    next_hold: u64,
+}
+
+impl CoordDelayNode {
+    fn release(&mut self, hold: u64, ctx: &mut Context<JsonMessage>) {
+        if let Some(value) = self.held.remove(&hold) {
+            ctx.cancel_timer(&format!("{}-NODE-{}|{}", RELEASE_TIMER, self.id, hold));
+            self.inner.borrow_mut().on_message(value.msg, value.from, ctx);
+        }
+    }
+}
+
+impl Node<JsonMessage> for CoordDelayNode {
+    fn id(&self) -> &String {
+        &self.id
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        if let Some(round) = MessageFields::of(&msg).round {
+            let mut stats = self.stats.borrow_mut();
+            stats.last_round = stats.last_round.max(round);
+        }
+        let coord_value = if msg.tip == MessageCoordValue::TYPE && from != self.id {
+            MessageCoordValue::decode(&msg).ok()
+        } else {
+            None
+        };
+        if let Some(coord_value) = coord_value {
+            let instance = message_instance(&coord_value);
+            let round = coord_value.round;
+            if round > self.fired.get(&instance).copied().unwrap_or(0) {
+                let hold = self.next_hold;
+                self.next_hold += 1;
+                self.held.insert(hold, HeldValue { msg, from, instance, round });
+                ctx.set_timer(&format!("{}-NODE-{}|{}", RELEASE_TIMER, self.id, hold), self.max_delay);
+                return;
+            }
+        }
+        self.inner.borrow_mut().on_message(msg, from, ctx);
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        self.inner.borrow_mut().on_local_message(msg, ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        if timer.starts_with(RELEASE_TIMER) {
+            // the delay ran out before the timer fired
+            let hold: u64 = timer.rsplit('|').next().unwrap().parse().unwrap();
+            if let Some(value) = self.held.remove(&hold) {
+                self.stats.borrow_mut().overcome
+                    .entry((self.id.clone(), value.instance))
+                    .or_insert(value.round);
+                self.inner.borrow_mut().on_message(value.msg, value.from, ctx);
+            }
+            return;
+        }
+
+        let is_coord_timer = timer.starts_with(COORD_TIMER);
+        let instance = timer_instance(&timer);
+        self.inner.borrow_mut().on_timer(timer, ctx);
+        if is_coord_timer {
+            let round = self.fired.entry(instance.clone()).or_insert(0);
+            *round += 1;
+            let round = *round;
+            let due: Vec<u64> = self.held.iter()
+                .filter(|(_, value)| value.instance == instance && value.round <= round)
+                .map(|(hold, _)| *hold)
+                .collect();
+            self.stats.borrow_mut().forced += due.len() as u64;
+            for hold in due {
+                self.release(hold, ctx);
+            }
+        }
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.inner.borrow_mut().max_size()
+    }
+}
+
+/// Builds nodes with `inner` and wraps them into `CoordDelayNode`.
+pub struct CoordDelayFactory<'a> {
+    inner: &'a dyn NodeFactory,
+    max_delay: f64,
+    stats: Rc<RefCell<CoordDelayStats>>,
+}
+
+impl<'a> CoordDelayFactory<'a> {
+    pub fn new(inner: &'a dyn NodeFactory, max_delay: f64) -> Self {
+        CoordDelayFactory { inner, max_delay, stats: rc!(refcell!(CoordDelayStats::default())) }
+    }
+
+    pub fn stats(&self) -> CoordDelayStats {
+        self.stats.borrow().clone()
+    }
+}
+
+impl NodeFactory for CoordDelayFactory<'_> {
+    fn build_node(
+        &self,
+        node_id: &str,
+        node_ids: &[String],
+        faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+        let inner = self.inner.build_node(node_id, node_ids, faulty_count, seed);
+        rc!(refcell!(CoordDelayNode {
+            id: node_id.to_string(),
+            inner,
+            max_delay: self.max_delay,
+            stats: self.stats.clone(),
+            fired: BTreeMap::new(),
+            held: BTreeMap::new(),
+            next_hold: 0,
+        }))
+    }
+}
+
+/// Runs a consensus against the coordinator delay adversary for every delay
+/// bound in `max_delays`, up to a message of a round past `max_round`,
+/// checks the consensus properties and prints how many rounds the timeouts
+/// took to overcome the adversary.
+pub fn run_coord_delay_test<F>(
+    config: &TestConfig,
+    max_delays: &[f64],
+    max_round: u64,
+    gen_init_values: F,
+    validity: Validity
+) -> TestResult
+where
+    F: Fn(&mut System<JsonMessage>) -> Vec<u64>
+{
+    for &max_delay in max_delays {
+        let factory = CoordDelayFactory::new(config.node_factory, max_delay);
+        let mut adversary_config = *config;
+        adversary_config.node_factory = &factory;
+        let mut sys = super::build_system(&adversary_config);
+        let nodes = sys.get_node_ids();
+
+        let init_values = gen_init_values(&mut sys);
+        let init_values = super::get_init_values(config, init_values);
+        super::send_init_messages(&mut sys, &init_values);
+
+        while factory.stats.borrow().last_round <= max_round && sys.step() {}
+
+        super::check_consensus_properties(&mut sys, &nodes, &init_values, validity)
+            .map_err(|e| format!("Coordinator delay {}: {}", max_delay, e))?;
+        let stats = factory.stats();
+        println!(
+            "coord delay: max_delay={} forced={} overcome_round={} last_round={}",
+            max_delay,
+            stats.forced,
+            stats.overcome_round().map_or("-".to_string(), |round| round.to_string()),
+            stats.last_round
+        );
+    }
+    Ok(true)
+}
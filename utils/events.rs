@@ -0,0 +1,247 @@
+use std::cell::{ Cell, RefCell };
+use std::fmt;
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use env_logger::Logger;
+use log::{ LevelFilter, Log, Metadata, Record };
+
+use dslib::pynode::JsonMessage;
+
+/// Event of a simulated run, recovered from the trace records of dslib.
+#[derive(Clone, Debug)]
+pub enum Event {
+    MessageSend { time: f64, src: String, dest: String, msg: JsonMessage },
+    MessageReceive { time: f64, src: String, dest: String, msg: JsonMessage },
+    /// dslib doesn't log the drop time, so the time of the previous event is used
+    MessageDrop { time: f64, src: String, dest: String, msg: JsonMessage },
+    LocalMessageSend { time: f64, node: String, msg: JsonMessage },
+    LocalMessageReceive { time: f64, node: String, msg: JsonMessage },
+    /// Timers set and cancelled by Python nodes, recorded only with a trace
+    /// (see `trace::start`)
+    TimerSet { time: f64, node: String, timer: String, delay: f64 },
+    TimerCancel { time: f64, node: String, timer: String },
+    TimerFired { time: f64, node: String, timer: String },
+    NodeCrashed { time: f64, node: String },
+    NodeDisconnected { time: f64, node: String },
+    NodeConnected { time: f64, node: String },
+    /// Any other network change (partitions, links, recoveries)
+    Network { time: f64, description: String },
+}
+
+impl Event {
+    pub fn time(&self) -> f64 {
+        match self {
+            Event::MessageSend { time, .. }
+            | Event::MessageReceive { time, .. }
+            | Event::MessageDrop { time, .. }
+            | Event::LocalMessageSend { time, .. }
+            | Event::LocalMessageReceive { time, .. }
+            | Event::TimerSet { time, .. }
+            | Event::TimerCancel { time, .. }
+            | Event::TimerFired { time, .. }
+            | Event::NodeCrashed { time, .. }
+            | Event::NodeDisconnected { time, .. }
+            | Event::NodeConnected { time, .. }
+            | Event::Network { time, .. } => *time,
+        }
+    }
+}
+
+/// Same format as the trace records of dslib.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::MessageSend { time, src, dest, msg } => write!(f, "{:>9.3} {:>10} --> {:<10} {:?}", time, src, dest, msg),
+            Event::MessageReceive { time, src, dest, msg } => write!(f, "{:>9.3} {:>10} <-- {:<10} {:?}", time, dest, src, msg),
+            Event::MessageDrop { src, dest, msg, .. } => write!(f, "{:>9} {:>10} --x {:<10} {:?} <-- message dropped", "!!!", src, dest, msg),
+            Event::LocalMessageSend { time, node, msg } => write!(f, "{:>9.3} {:>10} >>> {:<10} {:?}", time, node, "local", msg),
+            Event::LocalMessageReceive { time, node, msg } => write!(f, "{:>9.3} {:>10} <<< {:<10} {:?}", time, node, "local", msg),
+            Event::TimerSet { time, node, timer, delay } => write!(f, "{:>9.3} {:>10} +-- {:<10} {}", time, node, timer, delay),
+            Event::TimerCancel { time, node, timer } => write!(f, "{:>9.3} {:>10} x-- {:<10}", time, node, timer),
+            Event::TimerFired { time, node, timer } => write!(f, "{:>9.3} {:>10} !-- {:<10}", time, node, timer),
+            Event::NodeCrashed { time, node } => write!(f, "{:>9.3} {:>10} CRASHED!", time, node),
+            Event::NodeDisconnected { time, node } => write!(f, "{:>9.3} {:>10} DISCONNECTED", time, node),
+            Event::NodeConnected { time, node } => write!(f, "{:>9.3} {:>10} CONNECTED", time, node),
+            Event::Network { description, .. } => write!(f, "{}", description),
+        }
+    }
+}
+
+/// Set once `CapturingLogger` is installed, the logger is global to the process.
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static EVENTS: RefCell<Vec<Event>> = const { RefCell::new(Vec::new()) };
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Stops (or resumes) printing the trace to the console, events are still
+/// recorded. Used by runs which replay the system many times.
+pub fn set_quiet(quiet: bool) {
+    QUIET.with(|q| q.set(quiet));
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.with(Cell::get)
+}
+
+/// Whether `CapturingLogger` is installed: without it no events are recorded.
+pub fn is_capturing() -> bool {
+    CAPTURING.load(Ordering::Relaxed)
+}
+
+/// Forgets the events of the previous run, called when a new system is built.
+pub fn clear() {
+    EVENTS.with(|events| events.borrow_mut().clear());
+}
+
+/// Adds an event which dslib doesn't log.
+pub fn record(event: Event) {
+    super::trace::on_event(&event);
+    EVENTS.with(|events| events.borrow_mut().push(event));
+}
+
+pub fn get_events() -> Vec<Event> {
+    EVENTS.with(|events| events.borrow().clone())
+}
+
+/// Events from the index `start` on.
+pub fn get_events_from(start: usize) -> Vec<Event> {
+    EVENTS.with(|events| events.borrow().get(start..).map_or_else(Vec::new, <[Event]>::to_vec))
+}
+
+pub fn event_count() -> usize {
+    EVENTS.with(|events| events.borrow().len())
+}
+
+fn strip_colors(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip "ESC [ ... m"
+            for c in chars.by_ref() {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn next_token(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    Some(line.split_once(' ').unwrap_or((line, "")))
+}
+
+fn parse_message(text: &str) -> JsonMessage {
+    let (tip, data) = text.trim().split_once(' ').unwrap_or((text.trim(), ""));
+    JsonMessage::new(tip, data)
+}
+
+/// Parses a line produced by dslib's `t!` macro. `last_time` is used for
+/// records which don't carry the simulated time.
+fn parse_event(line: &str, last_time: f64) -> Option<Event> {
+    let line = strip_colors(line);
+    let (t0, rest) = next_token(&line)?;
+    let (t1, rest) = next_token(rest)?;
+
+    if t0 == "NETWORK" || t1 == "NETWORK" {
+        let time = t0.parse().unwrap_or(last_time);
+        return Some(Event::Network { time, description: line.trim().to_string() });
+    }
+    if t0 == "!!!" {
+        let (_, rest) = next_token(rest)?;
+        let (dest, rest) = next_token(rest)?;
+        let msg = rest.trim().trim_end_matches("<-- message dropped");
+        return Some(Event::MessageDrop {
+            time: last_time,
+            src: t1.to_string(),
+            dest: dest.to_string(),
+            msg: parse_message(msg),
+        });
+    }
+
+    let time: f64 = t0.parse().ok()?;
+    let node = t1.to_string();
+    let (t2, rest) = next_token(rest)?;
+    let event = match t2 {
+        "-->" => {
+            let (peer, msg) = next_token(rest)?;
+            let msg = msg.trim();
+            if msg == "LINK DISABLED" || msg == "LINK ENABLED" {
+                Event::Network { time, description: line.trim().to_string() }
+            } else {
+                Event::MessageSend { time, src: node, dest: peer.to_string(), msg: parse_message(msg) }
+            }
+        }
+        "<--" => {
+            let (peer, msg) = next_token(rest)?;
+            Event::MessageReceive { time, src: peer.to_string(), dest: node, msg: parse_message(msg) }
+        }
+        ">>>" => {
+            let (_, msg) = next_token(rest)?;
+            Event::LocalMessageSend { time, node, msg: parse_message(msg) }
+        }
+        "<<<" => {
+            let (_, msg) = next_token(rest)?;
+            Event::LocalMessageReceive { time, node, msg: parse_message(msg) }
+        }
+        "!--" => {
+            let (timer, _) = next_token(rest)?;
+            Event::TimerFired { time, node, timer: timer.to_string() }
+        }
+        "CRASHED!" => Event::NodeCrashed { time, node },
+        "DISCONNECTED" => Event::NodeDisconnected { time, node },
+        "CONNECTED" => Event::NodeConnected { time, node },
+        _ => Event::Network { time, description: line.trim().to_string() },
+    };
+    Some(event)
+}
+
+/// Wraps the console logger and records every dslib trace record as an `Event`,
+/// independently of the level printed to the console.
+pub struct CapturingLogger {
+    inner: Logger,
+}
+
+impl CapturingLogger {
+    pub fn init(inner: Logger) {
+        log::set_boxed_logger(Box::new(CapturingLogger { inner })).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+        CAPTURING.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target().starts_with("dslib") {
+            let line = record.args().to_string();
+            EVENTS.with(|events| {
+                let mut events = events.borrow_mut();
+                let last_time = events.last().map_or(0.0, |e| e.time());
+                if let Some(event) = parse_event(&line, last_time) {
+                    super::trace::on_event(&event);
+                    events.push(event);
+                }
+            });
+        }
+        if !is_quiet() && self.inner.matches(record) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
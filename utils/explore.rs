@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+
+use dslib::pynode::JsonMessage;
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use super::scheduler::{ run_scheduled, Pending, ScheduledFactory, Scheduler };
+use super::TestConfig;
+
+/// Bounds of `explore`.
+#[derive(Copy, Clone, Debug)]
+pub struct ExploreConfig {
+    /// Number of deliveries whose order is enumerated, the rest of a run is
+    /// delivered in the order of arrival
+    pub depth: usize,
+    /// Round after which a run is cut
+    pub max_round: u64,
+    /// Whether a run stops once every node has decided
+    pub until_decided: bool,
+}
+
+/// Follows a schedule prefix given as choices among the non-redundant
+/// candidates, then picks the first candidate up to the depth bound and
+/// the messages in the order of arrival after it.
+///
+/// Deliveries to different nodes commute, so of the two orders of such a
+/// pair only the one delivering to the smaller node id first is explored: a
+/// message is skipped if it was already pending before the previous
+/// delivery and goes to a smaller node id. If every pending message is
+/// skipped, all continuations are explored in other branches and the run stops.
+struct ExploringScheduler<'p> {
+    prefix: &'p [usize],
+    depth: usize,
+    /// Number of candidates at the levels past the prefix
+    counts: Vec<usize>,
+    delivered: Vec<Pending>,
+    /// Receiver of the previous delivery and the messages pending before it
+    previous: Option<(String, BTreeSet<u64>)>,
+    redundant: bool,
+}
+
+impl ExploringScheduler<'_> {
+    fn is_redundant(&self, pending: &Pending) -> bool {
+        self.previous.as_ref().is_some_and(|(to, seqs)| pending.to < *to && seqs.contains(&pending.seq))
+    }
+}
+
+impl Scheduler for ExploringScheduler<'_> {
+    fn name(&self) -> &str {
+        "explore"
+    }
+
+    fn next(&mut self, pending: &[Pending]) -> Option<usize> {
+        let level = self.delivered.len();
+        if level >= self.depth {
+            self.delivered.push(pending[0].clone());
+            return Some(0);
+        }
+        let candidates: Vec<usize> = (0..pending.len()).filter(|&idx| !self.is_redundant(&pending[idx])).collect();
+        if candidates.is_empty() {
+            self.redundant = true;
+            return None;
+        }
+        if level >= self.prefix.len() {
+            self.counts.push(candidates.len());
+        }
+        let choice = candidates[self.prefix.get(level).copied().unwrap_or(0)];
+        self.previous = Some((pending[choice].to.clone(), pending.iter().map(|p| p.seq).collect()));
+        self.delivered.push(pending[choice].clone());
+        Some(choice)
+    }
+}
+
+fn format_schedule(path: &[usize], delivered: &[Pending], depth: usize) -> String {
+    let mut result = format!(
+        "schedule {}:",
+        path.iter().map(usize::to_string).collect::<Vec<_>>().join(",")
+    );
+    for (idx, pending) in delivered.iter().enumerate().take(depth) {
+        result += &format!("\n  {:>3}. {} --> {} {} {}", idx + 1, pending.from, pending.to, pending.msg.tip, pending.msg.data);
+    }
+    if delivered.len() > depth {
+        result += &format!("\n  then {} deliveries in the order of arrival", delivered.len() - depth);
+    }
+    result
+}
+
+/// Enumerates the delivery orders of the first `depth` messages between
+/// nodes. Python nodes can't be cloned, so every schedule is replayed from
+/// the start: the system is built (with the Byzantine nodes of
+/// `config.byz_placement`, whose messages are scheduled too), `setup` sends the init messages, the
+/// schedule is run and `check` judges the result. Stops at the first
+/// failure and reports the schedule which led to it.
+pub fn explore<S, C>(config: &TestConfig, bounds: ExploreConfig, setup: S, check: C) -> TestResult
+where
+    S: Fn(&mut System<JsonMessage>),
+    C: Fn(&mut System<JsonMessage>) -> TestResult
+{
+    // chosen candidate and number of candidates at every level
+    let mut path: Vec<(usize, usize)> = Vec::new();
+    let mut schedules = 0;
+    let mut pruned = 0;
+    // thousands of replays would flood the console
+    super::events::set_quiet(true);
+    let result = loop {
+        let prefix: Vec<usize> = path.iter().map(|(choice, _)| *choice).collect();
+        let factory = ScheduledFactory::new(config.node_factory);
+        let byz_factory = config.byz_node_factory.map(|byz| factory.share(byz));
+        let mut explored_config = *config;
+        explored_config.node_factory = &factory;
+        explored_config.byz_node_factory = byz_factory.as_ref().map(|byz| byz as &dyn super::NodeFactory);
+        let mut sys = super::build_system_with_byz(&explored_config);
+        setup(&mut sys);
+
+        let mut scheduler = ExploringScheduler {
+            prefix: &prefix,
+            depth: bounds.depth,
+            counts: Vec::new(),
+            delivered: Vec::new(),
+            previous: None,
+            redundant: false,
+        };
+        run_scheduled(&mut sys, &factory, &mut scheduler, bounds.max_round, bounds.until_decided);
+        path.extend(scheduler.counts.iter().map(|count| (0, *count)));
+
+        if scheduler.redundant {
+            pruned += 1;
+        } else {
+            schedules += 1;
+            if let Err(e) = check(&mut sys) {
+                let choices: Vec<usize> = path.iter().map(|(choice, _)| *choice).collect();
+                break Err(format!("{}\n{}", e, format_schedule(&choices, &scheduler.delivered, bounds.depth)));
+            }
+        }
+
+        // next path in depth-first order
+        while let Some((choice, count)) = path.pop() {
+            if choice + 1 < count {
+                path.push((choice + 1, count));
+                break;
+            }
+        }
+        if path.is_empty() {
+            break Ok(true);
+        }
+    };
+    super::events::set_quiet(false);
+    println!("explore: depth={} schedules={} pruned={}", bounds.depth, schedules, pruned);
+    result
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use dslib::pynode::JsonMessage;
+use dslib::system::System;
+
+use super::events::{ self, Event };
+
+/// Fault injected by a timeline, nodes are given by their indices.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    Crash(u32),
+    Disconnect(u32),
+    Connect(u32),
+    /// Disables the links between every two groups
+    Partition(Vec<Vec<u32>>),
+    /// Enables every link and connects every node back
+    Heal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedFault {
+    pub time: f64,
+    pub fault: Fault,
+}
+
+/// Entry of a TOML or JSON timeline: the time and exactly one fault, e.g.
+/// `{ at = 4.0, partition = [[0, 1], [2, 3]] }`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FaultEntry {
+    at: f64,
+    crash: Option<u32>,
+    disconnect: Option<u32>,
+    connect: Option<u32>,
+    partition: Option<Vec<Vec<u32>>>,
+    heal: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimelineFile {
+    faults: Vec<FaultEntry>,
+}
+
+impl FaultEntry {
+    fn into_timed_fault(self) -> Result<TimedFault, String> {
+        let mut faults = Vec::new();
+        faults.extend(self.crash.map(Fault::Crash));
+        faults.extend(self.disconnect.map(Fault::Disconnect));
+        faults.extend(self.connect.map(Fault::Connect));
+        faults.extend(self.partition.map(Fault::Partition));
+        if self.heal == Some(true) {
+            faults.push(Fault::Heal);
+        }
+        if faults.len() != 1 {
+            return Err(format!("Fault at {} must have exactly one action, found {}", self.at, faults.len()));
+        }
+        Ok(TimedFault { time: self.at, fault: faults.pop().unwrap() })
+    }
+}
+
+fn parse_node(text: &str) -> Result<u32, String> {
+    text.trim().parse().map_err(|_| format!("Bad node id {:?}", text))
+}
+
+/// Parses "{0,1}|{2,3}".
+fn parse_groups(text: &str) -> Result<Vec<Vec<u32>>, String> {
+    text.split('|')
+        .map(|group| {
+            let group = group.trim();
+            let inner = group
+                .strip_prefix('{')
+                .and_then(|g| g.strip_suffix('}'))
+                .ok_or_else(|| format!("Bad partition group {:?}, expected {{0,1}}", group))?;
+            inner.split(',').filter(|id| !id.trim().is_empty()).map(parse_node).collect()
+        })
+        .collect()
+}
+
+/// Parses one "t=<time> <fault> [args]" event of the inline syntax.
+fn parse_inline_fault(text: &str) -> Result<TimedFault, String> {
+    let (time, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let time: f64 = time
+        .strip_prefix("t=")
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| format!("Bad fault time in {:?}, expected t=<time>", text))?;
+    let (action, args) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+    let fault = match action {
+        "crash" => Fault::Crash(parse_node(args)?),
+        "disconnect" => Fault::Disconnect(parse_node(args)?),
+        "connect" => Fault::Connect(parse_node(args)?),
+        "partition" => Fault::Partition(parse_groups(args)?),
+        "heal" if args.trim().is_empty() => Fault::Heal,
+        _ => return Err(format!("Unknown fault {:?}", text)),
+    };
+    Ok(TimedFault { time, fault })
+}
+
+/// Faults injected at given simulated times, shared by every protocol suite.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FaultTimeline {
+    /// Sorted by time
+    pub faults: Vec<TimedFault>,
+}
+
+impl FaultTimeline {
+    fn new(mut faults: Vec<TimedFault>) -> Result<Self, String> {
+        if let Some(fault) = faults.iter().find(|f| f.time.is_nan() || f.time < 0.0) {
+            return Err(format!("Bad fault time {}", fault.time));
+        }
+        faults.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        Ok(FaultTimeline { faults })
+    }
+
+    /// Parses the inline syntax: events separated by ';' or new lines, e.g.
+    /// "t=1.0 crash 2; t=2.5 disconnect 3; t=4 partition {0,1}|{2,3}; t=8 heal".
+    pub fn parse_inline(text: &str) -> Result<Self, String> {
+        let faults = text
+            .split([';', '\n'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(parse_inline_fault)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(faults)
+    }
+
+    /// Loads a timeline from a `.toml` or `.json` file with a `faults` list,
+    /// any other file is read in the inline syntax.
+    pub fn load_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can't read fault timeline {}: {}", path, e))?;
+        let entries = if path.ends_with(".toml") {
+            toml::from_str::<TimelineFile>(&text).map_err(|e| e.to_string())
+        } else if path.ends_with(".json") {
+            serde_json::from_str::<TimelineFile>(&text).map_err(|e| e.to_string())
+        } else {
+            return Self::parse_inline(&text).map_err(|e| format!("{}: {}", path, e));
+        };
+        let faults = entries
+            .map_err(|e| format!("Can't parse fault timeline {}: {}", path, e))?
+            .faults
+            .into_iter()
+            .map(FaultEntry::into_timed_fault)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(faults)
+    }
+
+    /// Command line entry point: `spec` is a timeline file or an inline timeline.
+    /// Exits if it can't be loaded.
+    pub fn from_arg(spec: &str) -> Self {
+        let result = if Path::new(spec).is_file() {
+            Self::load_file(spec)
+        } else {
+            Self::parse_inline(spec)
+        };
+        result.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        })
+    }
+
+    /// Steps the system through the timeline and injects every fault once
+    /// its time is reached. dslib can only step event by event, so a fault
+    /// comes right after the first event at or after its time, or right away
+    /// if there are no events left. Returns the nodes which are faulty
+    /// afterwards: crashed, or disconnected and not connected back.
+    pub fn run(&self, sys: &mut System<JsonMessage>) -> Result<Vec<String>, String> {
+        let node_count = sys.get_node_ids().len() as u32;
+        let node = |id: &u32| {
+            if *id < node_count {
+                Ok(id.to_string())
+            } else {
+                Err(format!("Fault timeline refers to node {}, but there are {} nodes", id, node_count))
+            }
+        };
+
+        let mut crashed = BTreeSet::new();
+        let mut disconnected = BTreeSet::new();
+        for TimedFault { time, fault } in self.faults.iter() {
+            if *time > sys.time() {
+                sys.step_for_duration(time - sys.time());
+            }
+            match fault {
+                Fault::Crash(id) => {
+                    let id = node(id)?;
+                    if !sys.node_is_crashed(&id) {
+                        sys.crash_node(&id);
+                    }
+                    crashed.insert(id);
+                }
+                Fault::Disconnect(id) => {
+                    let id = node(id)?;
+                    sys.disconnect_node(&id);
+                    disconnected.insert(id);
+                }
+                Fault::Connect(id) => {
+                    let id = node(id)?;
+                    sys.connect_node(&id);
+                    disconnected.remove(&id);
+                }
+                Fault::Partition(groups) => {
+                    let groups = groups
+                        .iter()
+                        .map(|group| group.iter().map(node).collect::<Result<Vec<_>, _>>())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for (idx, group1) in groups.iter().enumerate() {
+                        for group2 in groups[idx + 1..].iter() {
+                            let group1: Vec<&str> = group1.iter().map(String::as_str).collect();
+                            let group2: Vec<&str> = group2.iter().map(String::as_str).collect();
+                            sys.make_partition(&group1, &group2);
+                        }
+                    }
+                }
+                Fault::Heal => {
+                    // dslib doesn't log network resets
+                    let description = format!("{:>9.3} NETWORK RESET", sys.time());
+                    log::trace!("{}", description);
+                    events::record(Event::Network { time: sys.time(), description });
+                    sys.reset_network();
+                    disconnected.clear();
+                }
+            }
+        }
+        Ok(crashed.union(&disconnected).cloned().collect())
+    }
+}
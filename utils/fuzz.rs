@@ -0,0 +1,342 @@
+use std::panic::{ self, AssertUnwindSafe };
+
+use dslib::test::TestResult;
+
+use super::{ matrix, report, shrink, trace, TestConfig };
+
+/// Seeds for the multi-seed mode: `--seeds N` runs N seeds starting from
+/// `--seed`, `--seed-range a..b` (or `a..=b`) runs the given range.
+/// Returns `None` if neither is given. Exits on a malformed range.
+pub fn get_seeds(seed: u64, seed_count: Option<u64>, seed_range: Option<&str>) -> Option<Vec<u64>> {
+    if let Some(count) = seed_count {
+        return Some((seed..seed.saturating_add(count)).collect());
+    }
+    let range = seed_range?;
+    let parsed = match range.split_once("..") {
+        Some((from, to)) => match to.strip_prefix('=') {
+            Some(to) => from.parse::<u64>().and_then(|from| to.parse::<u64>().map(|to| (from..=to).collect())),
+            None => from.parse::<u64>().and_then(|from| to.parse::<u64>().map(|to| (from..to).collect())),
+        },
+        None => range.parse::<u64>().map(|seed| vec![seed]),
+    };
+    match parsed {
+        Ok(seeds) => Some(seeds),
+        Err(e) => {
+            eprintln!("Bad seed range {}: {}", range, e);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "_-./=:,".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Command line which reruns a single scenario: the current arguments
+/// without the multi-seed options, with `args` given as (long flag, short
+/// flag, value) replacing their current values. An empty value removes the flag.
+pub fn repro_command(args: &[(&str, Option<char>, String)]) -> String {
+    let mut with_value = vec!["--seeds".to_string(), "--seed-range".to_string()];
+    for (long, short, _) in args {
+        with_value.push(long.to_string());
+        with_value.extend(short.map(|c| format!("-{}", c)));
+    }
+
+    let mut result = Vec::new();
+    let mut skip_value = false;
+    for arg in std::env::args().skip(1) {
+        if skip_value {
+            skip_value = false;
+            continue;
+        }
+        let flag = arg.split('=').next().unwrap();
+        if with_value.iter().any(|f| f == flag) {
+            skip_value = !arg.contains('=');
+            continue;
+        }
+        // short flag with the value attached, e.g. -s42
+        if !arg.starts_with("--") && arg.len() > 2 && with_value.iter().any(|f| arg.starts_with(f.as_str()) && f.len() == 2) {
+            continue;
+        }
+        if arg == "--shrink" {
+            continue;
+        }
+        result.push(shell_quote(&arg));
+    }
+    for (long, _, value) in args.iter().filter(|(_, _, value)| !value.is_empty()) {
+        result.push(format!("{} {}", long, shell_quote(value)));
+    }
+    format!("cargo run -- {}", result.join(" "))
+}
+
+/// Runs the test, turning panics into failures so the other runs go on.
+pub fn run_catching<F: Fn(&TestConfig) -> TestResult>(func: F, config: &TestConfig) -> TestResult {
+    match panic::catch_unwind(AssertUnwindSafe(|| func(config))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload.downcast_ref::<String>().cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(format!("panicked: {}", message))
+        }
+    }
+}
+
+pub type TestFn = fn(&TestConfig) -> TestResult;
+
+/// Whether `name` matches the glob `pattern`, where `*` matches any text and
+/// `?` any single character. Letter case is ignored.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    // position after the last `*` and the name position it currently matches
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            backtrack = Some((star_p, star_n + 1));
+            p = star_p;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether the test name, with or without the group added by
+/// `SeededTestSuite::add_group`, matches the glob `pattern`.
+pub fn test_matches(pattern: &str, name: &str) -> bool {
+    glob_match(pattern, name) || name.split_once(": ").is_some_and(|(_, test)| glob_match(pattern, test))
+}
+
+/// Test suite with the output of dslib's `TestSuite`, which also keeps the
+/// registered tests, so that every test can be rerun with other seeds or
+/// replayed from a trace.
+pub struct SeededTestSuite<'a> {
+    tests: Vec<(String, TestFn, TestConfig<'a>)>,
+}
+
+fn run_traced(name: &str, func: TestFn, config: &TestConfig) -> TestResult {
+    trace::begin_test(name, config.seed);
+    let result = func(config);
+    trace::end_test(&result);
+    result
+}
+
+/// Runs the test catching panics and adds its outcome to the report.
+fn run_reported(name: &str, func: TestFn, config: &TestConfig) -> TestResult {
+    report::begin_test();
+    let result = run_catching(|config| run_traced(name, func, config), config);
+    report::end_test(name, config, &result);
+    result
+}
+
+impl<'a> SeededTestSuite<'a> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self { tests: Vec::new() }
+    }
+
+    pub fn add(&mut self, name: &str, f: TestFn, config: TestConfig<'a>) {
+        self.tests.push((name.to_string(), f, config));
+    }
+
+    /// Adds the tests registered by `add_tests`, named "`group`: <test name>".
+    pub fn add_group(&mut self, group: &str, add_tests: impl FnOnce(&mut SeededTestSuite<'a>)) {
+        let mut suite = SeededTestSuite::new();
+        add_tests(&mut suite);
+        for (name, f, config) in suite.tests {
+            self.tests.push((format!("{}: {}", group, name), f, config));
+        }
+    }
+
+    /// Keeps only the tests whose names match `pattern`, see `test_matches`.
+    pub fn retain_matching(&mut self, pattern: &str) {
+        self.tests.retain(|(name, _, _)| test_matches(pattern, name));
+    }
+
+    /// Keeps only the tests of the groups for which `keep` returns true.
+    pub fn retain_groups(&mut self, keep: impl Fn(&str) -> bool) {
+        self.tests.retain(|(name, _, _)| name.split_once(": ").is_some_and(|(group, _)| keep(group)));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.tests.iter().map(|(name, _, _)| name.as_str()).collect()
+    }
+
+    /// Replaces every test by its instances in the test matrix of `suite_config`.
+    pub fn expand(&mut self, suite_config: &matrix::SuiteConfig) {
+        for (name, func, config) in std::mem::take(&mut self.tests) {
+            for (instance, config) in suite_config.instances(&name, &config) {
+                self.tests.push((instance, func, config));
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// Runs all tests, a panic fails only its test, and exits with code 1 if some failed.
+    pub fn run(&mut self) {
+        let mut failed_tests = Vec::new();
+        for (name, func, config) in self.tests.iter() {
+            println!("\n--- {} ---\n", name);
+            match run_reported(name, *func, config) {
+                Ok(_) => println!("\nPASSED\n"),
+                Err(e) => {
+                    println!("\nFAILED: {}\n", e);
+                    failed_tests.push((name, e));
+                }
+            }
+        }
+        println!("-------------------------------------------------------------------------------");
+        println!("\nPassed {} from {} tests\n", self.tests.len() - failed_tests.len(), self.tests.len());
+        report::finish();
+        if failed_tests.is_empty() {
+            std::process::exit(0);
+        }
+        println!("Failed tests:");
+        for (name, e) in failed_tests {
+            println!("- {}: {}", name, e);
+        }
+        println!();
+        std::process::exit(1);
+    }
+
+    pub fn run_test(&mut self, name: &str) {
+        for (test, func, config) in self.tests.iter().filter(|(test, _, _)| test == name) {
+            println!("\n--- {} ---\n", test);
+            match run_reported(test, *func, config) {
+                Ok(_) => println!("\nPASSED\n"),
+                Err(e) => println!("\nFAILED: {}\n", e),
+            }
+        }
+        report::finish();
+    }
+
+    /// Reruns the tests of a trace with their recorded seeds, feeding the
+    /// recorded delivery order back, and checks that every test produces the
+    /// same trace. Exits with code 1 if some trace differs.
+    pub fn replay(&self, recording: &trace::Recording) -> ! {
+        let mut diverged = 0;
+        for recorded in recording.tests.iter() {
+            let (name, func, config) = match self.tests.iter().find(|(name, _, _)| *name == recorded.name) {
+                Some(test) => test,
+                None => {
+                    eprintln!("No test {}", recorded.name);
+                    std::process::exit(2);
+                }
+            };
+            let mut config = *config;
+            config.seed = recorded.seed;
+
+            trace::begin_replay(recorded);
+            let result = run_catching(|config| run_traced(name, *func, config), &config);
+            let replayed = trace::end_replay();
+            let outcome = match &result {
+                Ok(_) => "PASSED".to_string(),
+                Err(e) => format!("FAILED: {}", e),
+            };
+            match trace::compare(&recorded.lines, &replayed) {
+                Ok(count) => println!("seed {} {}: same trace, {} events, {}", recorded.seed, name, count, outcome),
+                Err(e) => {
+                    diverged += 1;
+                    println!("seed {} {}: trace differs at {}, {}", recorded.seed, name, e, outcome);
+                }
+            }
+        }
+        println!("-------------------------------------------------------------------------------");
+        println!("\nReplayed {} tests, {} with a different trace\n", recording.tests.len(), diverged);
+        std::process::exit(if diverged == 0 { 0 } else { 1 });
+    }
+
+    /// Runs every test (or only `test`, if given) with every seed, going on
+    /// after failures, then prints the failed runs with the command lines
+    /// reproducing them. With `shrink` the first failure of every test is
+    /// also shrunk to a smaller scenario. Exits with code 1 if some run failed.
+    pub fn run_seeds(&self, seeds: &[u64], test: Option<&str>, shrink: bool) -> ! {
+        let tests: Vec<_> = self.tests.iter().filter(|(name, _, _)| test.is_none_or(|t| t == name)).collect();
+        if tests.is_empty() {
+            eprintln!("No test {}", test.unwrap_or_default());
+            std::process::exit(2);
+        }
+
+        // panics are reported as failures, the default hook would print them twice
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+
+        let mut runs = 0;
+        let mut failed_runs = Vec::new();
+        for &seed in seeds {
+            for (name, func, config) in tests.iter() {
+                let mut config = *config;
+                config.seed = seed;
+                runs += 1;
+                match run_reported(name, *func, &config) {
+                    Ok(_) => println!("seed {} {}: PASSED", seed, name),
+                    Err(e) => {
+                        println!("seed {} {}: FAILED: {}", seed, name, e);
+                        failed_runs.push((seed, name, e));
+                    }
+                }
+            }
+        }
+
+        let mut shrunk_runs = Vec::new();
+        if shrink {
+            for (name, func, config) in tests.iter() {
+                let seed = match failed_runs.iter().find(|(_, failed, _)| *failed == name) {
+                    Some((seed, _, _)) => *seed,
+                    None => continue,
+                };
+                println!("Shrinking seed {} {}", seed, name);
+                let mut config = *config;
+                config.seed = seed;
+                if let Some(shrunk) = shrink::shrink(*func, &config) {
+                    shrunk_runs.push((name, shrunk));
+                }
+            }
+        }
+        panic::set_hook(default_hook);
+
+        println!("-------------------------------------------------------------------------------");
+        println!("\nPassed {} from {} runs ({} seeds)\n", runs - failed_runs.len(), runs, seeds.len());
+        report::finish();
+        if failed_runs.is_empty() {
+            std::process::exit(0);
+        }
+        println!("Failed runs:");
+        for (seed, name, e) in failed_runs {
+            println!("- seed {} {}: {}", seed, name, e);
+            println!("  {}", repro_command(&[
+                ("--seed", Some('s'), seed.to_string()),
+                ("--test", Some('t'), name.to_string()),
+            ]));
+        }
+        println!();
+        if !shrunk_runs.is_empty() {
+            println!("Shrunk failures:");
+            for (name, shrunk) in shrunk_runs {
+                println!(
+                    "- {}: n={} f={} seed={} after {} runs: {}",
+                    name, shrunk.node_count, shrunk.faulty_count, shrunk.seed, shrunk.runs, shrunk.error
+                );
+                println!("  {}", shrunk.repro_command(name));
+            }
+            println!();
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use sugars::{ rc, refcell };
+
+use dslib::node::{ Context, Node };
+use dslib::pynode::JsonMessage;
+
+use super::NodeFactory;
+
+/// Timers of `GstNode` start with this prefix, the inner node never sees them.
+const HOLD_TIMER: &str = "GST-HOLD";
+
+/// Partial synchrony with a Global Stabilization Time. Before `gst` every
+/// message is held back for a random extra delay of up to `pre_gst_delay`,
+/// but never past `gst`. The network delay itself is bounded by `delta`
+/// (see `network_delays`), so every message arrives by max(send time, gst) + delta.
+#[derive(Copy, Clone, Debug)]
+pub struct GstModel {
+    pub gst: f64,
+    pub delta: f64,
+    pub pre_gst_delay: f64,
+}
+
+impl GstModel {
+    /// Network delays for `NetworkConfig::delays`
+    pub fn network_delays(&self) -> (f64, f64) {
+        (0.0, self.delta)
+    }
+}
+
+/// Wraps a node and holds back the messages it receives before GST.
+pub struct GstNode {
+    id: String,
+    inner: Rc<RefCell<dyn Node<JsonMessage>>>,
+    model: GstModel,
+    /// Held messages with their senders, by the number in the timer name
+    held: BTreeMap<u64, (JsonMessage, String)>,
+    next_hold: u64,
+}
+
+impl Node<JsonMessage> for GstNode {
+    fn id(&self) -> &String {
+        &self.id
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        let time = ctx.time();
+        if time < self.model.gst && from != self.id {
+            let release = (time + ctx.rand() * self.model.pre_gst_delay).min(self.model.gst);
+            if release > time {
+                let hold = self.next_hold;
+                self.next_hold += 1;
+                self.held.insert(hold, (msg, from));
+                ctx.set_timer(&format!("{}-NODE-{}|{}", HOLD_TIMER, self.id, hold), release - time);
+                return;
+            }
+        }
+        self.inner.borrow_mut().on_message(msg, from, ctx);
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        self.inner.borrow_mut().on_local_message(msg, ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        if timer.starts_with(HOLD_TIMER) {
+            let hold: u64 = timer.rsplit('|').next().unwrap().parse().unwrap();
+            if let Some((msg, from)) = self.held.remove(&hold) {
+                self.inner.borrow_mut().on_message(msg, from, ctx);
+            }
+            return;
+        }
+        self.inner.borrow_mut().on_timer(timer, ctx);
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.inner.borrow_mut().max_size()
+    }
+}
+
+/// Builds nodes with `inner` and wraps them into `GstNode`.
+pub struct GstFactory<'a> {
+    inner: &'a dyn NodeFactory,
+    model: GstModel,
+}
+
+impl<'a> GstFactory<'a> {
+    pub fn new(inner: &'a dyn NodeFactory, model: GstModel) -> Self {
+        GstFactory { inner, model }
+    }
+}
+
+impl NodeFactory for GstFactory<'_> {
+    fn build_node(
+        &self,
+        node_id: &str,
+        node_ids: &[String],
+        faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+        let inner = self.inner.build_node(node_id, node_ids, faulty_count, seed);
+        rc!(refcell!(GstNode {
+            id: node_id.to_string(),
+            inner,
+            model: self.model,
+            held: BTreeMap::new(),
+            next_hold: 0,
+        }))
+    }
+}
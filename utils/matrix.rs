@@ -0,0 +1,183 @@
+use serde::Deserialize;
+use std::fs;
+
+use super::fuzz::test_matches;
+use super::{ NetworkConfig, TestConfig };
+
+/// Suite config file, e.g.
+///
+/// ```toml
+/// matrix = [[4, 1], [7, 2]]
+/// seeds = [42, 43]
+/// delays = ["1", "0.5..3"]
+///
+/// [[test]]
+/// name = "*EXPLORE*"
+/// matrix = [[4, 1]]
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    matrix: Option<Vec<(u32, u32)>>,
+    seeds: Option<Vec<u64>>,
+    delays: Option<Vec<String>>,
+    #[serde(default)]
+    test: Vec<TestEntry>,
+}
+
+/// Dimensions of the tests matching the glob `name`, see `fuzz::test_matches`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestEntry {
+    name: String,
+    matrix: Option<Vec<(u32, u32)>>,
+    seeds: Option<Vec<u64>>,
+    delays: Option<Vec<String>>,
+}
+
+/// Values which a test is run with. A dimension which is not given keeps the
+/// value of the command line and doesn't appear in the instance names.
+#[derive(Clone, Debug, Default)]
+struct Dimensions {
+    /// (n, f) pairs
+    matrix: Option<Vec<(u32, u32)>>,
+    seeds: Option<Vec<u64>>,
+    /// Delays as written in the file and parsed
+    delays: Option<Vec<(String, (f64, f64))>>,
+}
+
+impl Dimensions {
+    fn parse(
+        matrix: Option<Vec<(u32, u32)>>,
+        seeds: Option<Vec<u64>>,
+        delays: Option<Vec<String>>
+    ) -> Result<Self, String> {
+        if let Some(&(n, f)) = matrix.iter().flatten().find(|(n, f)| f >= n) {
+            return Err(format!("bad (n, f) pair [{}, {}]: f must be less than n", n, f));
+        }
+        let delays = delays.map(|delays| {
+            delays.into_iter().map(|text| {
+                let text = text.trim().to_string();
+                let parsed = NetworkConfig::parse_delays(&text);
+                (text, parsed)
+            }).collect()
+        });
+        Ok(Self { matrix, seeds, delays })
+    }
+
+    /// Takes the dimensions given in `other`.
+    fn merge(&mut self, other: &Dimensions) {
+        if other.matrix.is_some() {
+            self.matrix = other.matrix.clone();
+        }
+        if other.seeds.is_some() {
+            self.seeds = other.seeds.clone();
+        }
+        if other.delays.is_some() {
+            self.delays = other.delays.clone();
+        }
+    }
+}
+
+/// Test matrix loaded with `--config`: every test runs once for every
+/// combination of (n, f) pair, seed and delays, which is added to the test
+/// name, e.g. "TEST SIMPLE [n=7 f=2 seed=43 delays=0.5..3]", so that a
+/// single combination can be rerun with `--test`.
+pub struct SuiteConfig {
+    dimensions: Dimensions,
+    /// (glob of test names, dimensions) in the file order, every matching
+    /// entry replaces the dimensions it gives
+    tests: Vec<(String, Dimensions)>,
+}
+
+impl SuiteConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can't read suite config {}: {}", path, e))?;
+        let file: ConfigFile = toml::from_str(&text).map_err(|e| format!("Can't parse suite config {}: {}", path, e))?;
+        let dimensions = Dimensions::parse(file.matrix, file.seeds, file.delays)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let mut tests = Vec::new();
+        for entry in file.test {
+            let test = Dimensions::parse(entry.matrix, entry.seeds, entry.delays)
+                .map_err(|e| format!("{}: test {}: {}", path, entry.name, e))?;
+            tests.push((entry.name, test));
+        }
+        Ok(Self { dimensions, tests })
+    }
+
+    /// Command line entry point. Exits if the config can't be loaded, or if
+    /// it sets seeds while the tests are run with several seeds (`multi_seed`).
+    pub fn from_arg(path: &str, multi_seed: bool) -> Self {
+        let config = Self::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        });
+        if multi_seed && config.has_seeds() {
+            eprintln!("Suite config {} sets seeds, it can't be run with --seeds, --seed-range or --shrink", path);
+            std::process::exit(2);
+        }
+        config
+    }
+
+    fn has_seeds(&self) -> bool {
+        self.dimensions.seeds.is_some() || self.tests.iter().any(|(_, test)| test.seeds.is_some())
+    }
+
+    /// Instances of the test `name` registered with `config`, as (instance name, config).
+    pub fn instances<'a>(&self, name: &str, config: &TestConfig<'a>) -> Vec<(String, TestConfig<'a>)> {
+        let mut dimensions = self.dimensions.clone();
+        for (_, test) in self.tests.iter().filter(|(pattern, _)| test_matches(pattern, name)) {
+            dimensions.merge(test);
+        }
+        let matrix: Vec<Option<(u32, u32)>> = match dimensions.matrix {
+            Some(matrix) => matrix.into_iter().map(Some).collect(),
+            None => vec![None],
+        };
+        let seeds: Vec<Option<u64>> = match dimensions.seeds {
+            Some(seeds) => seeds.into_iter().map(Some).collect(),
+            None => vec![None],
+        };
+        let delays: Vec<Option<(String, (f64, f64))>> = match dimensions.delays {
+            Some(delays) => delays.into_iter().map(Some).collect(),
+            None => vec![None],
+        };
+
+        let mut instances = Vec::new();
+        for &pair in matrix.iter() {
+            for &seed in seeds.iter() {
+                for delay in delays.iter() {
+                    let mut instance = *config;
+                    let mut tags = Vec::new();
+                    if let Some((node_count, faulty_count)) = pair {
+                        instance.node_count = node_count;
+                        instance.faulty_count = faulty_count;
+                        tags.push(format!("n={} f={}", node_count, faulty_count));
+                    }
+                    if let Some(seed) = seed {
+                        instance.seed = seed;
+                        tags.push(format!("seed={}", seed));
+                    }
+                    if let Some((text, parsed)) = delay {
+                        instance.network.delays = Some(*parsed);
+                        tags.push(format!("delays={}", text));
+                    }
+                    let instance_name = if tags.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{} [{}]", name, tags.join(" "))
+                    };
+                    instances.push((instance_name, instance));
+                }
+            }
+        }
+        instances
+    }
+}
+
+/// Name of the test which the instance `name` was expanded from.
+pub fn base_name(name: &str) -> &str {
+    match (name.ends_with(']'), name.rfind(" [")) {
+        (true, Some(end)) => &name[..end],
+        _ => name,
+    }
+}
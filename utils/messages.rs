@@ -0,0 +1,242 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{ Deserialize, Serialize };
+
+use dslib::pynode::JsonMessage;
+
+/// Message of the Python nodes with a known type and JSON layout.
+pub trait ProtocolMessage: Serialize + DeserializeOwned {
+    /// `tip` of the message
+    const TYPE: &'static str;
+
+    /// Decodes the data of `msg`, failing with a description of the message
+    /// if its type is different or its data doesn't match the layout.
+    fn decode(msg: &JsonMessage) -> Result<Self, String> {
+        if msg.tip != Self::TYPE {
+            return Err(format!("Expected {} message, got {:?}", Self::TYPE, msg));
+        }
+        serde_json::from_str(&msg.data).map_err(|e| format!("Malformed {:?}: {}", msg, e))
+    }
+
+    fn encode(&self) -> JsonMessage {
+        JsonMessage::from(Self::TYPE, self)
+    }
+}
+
+/// Fields which several message types share, for code which handles any
+/// message (statistics, schedulers). Absent and malformed fields are `None`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MessageFields {
+    pub round: Option<u64>,
+    /// DBFT binary consensus instance
+    pub k: Option<u64>,
+    /// Reliable broadcast instance in DBFT
+    pub sender: Option<String>,
+}
+
+impl MessageFields {
+    pub fn of(msg: &JsonMessage) -> Self {
+        serde_json::from_str(&msg.data).unwrap_or_default()
+    }
+}
+
+/// Input of every protocol
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageInit {
+    pub value: u64,
+}
+
+/// Decision of a consensus protocol: with the round in PsyncBBC and SafeBBC,
+/// with the accepted proposals ("0;1;3") in DBFT, with neither in Ben-Or.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageResult {
+    pub value: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_proposals: Option<String>,
+}
+
+/// Value delivered by binary value broadcast
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageDelivery {
+    pub value: u64,
+}
+
+/// Value of a DELIVERY or ACCEPT message, the output of the broadcast protocols.
+pub fn delivered_value(msg: &JsonMessage) -> Result<u64, String> {
+    if msg.tip == MessageAccept::TYPE {
+        MessageAccept::decode(msg).map(|accept| accept.value)
+    } else {
+        MessageDelivery::decode(msg).map(|delivery| delivery.value)
+    }
+}
+
+/// Value accepted by reliable broadcast
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageAccept {
+    pub value: u64,
+}
+
+/// ECHO of binary value broadcast and reliable broadcast
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageEcho {
+    pub value: u64,
+}
+
+/// READY of reliable broadcast
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageReady {
+    pub value: u64,
+}
+
+/// Estimate of PsyncBBC, SafeBBC and DBFT (with the instance `k`)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageEst {
+    pub value: u64,
+    pub round: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<u64>,
+}
+
+/// Values of `bin_values` (PsyncBBC and DBFT: possibly only the coordinator's)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageAux {
+    pub bin_values: Vec<u64>,
+    pub round: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<u64>,
+}
+
+/// Value of the coordinator of the round
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageCoordValue {
+    pub value: u64,
+    pub round: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<u64>,
+}
+
+/// Reliable broadcast messages inside DBFT, `sender` is the broadcasting node
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageRbInit {
+    pub value: u64,
+    pub sender: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageRbEcho {
+    pub value: u64,
+    pub sender: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageRbReady {
+    pub value: u64,
+    pub sender: String,
+}
+
+/// Preference of a Ben-Or node
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageVote {
+    pub value: u64,
+    pub round: u64,
+}
+
+/// Proposal of a Ben-Or node: a value or '?' without a majority of votes
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessagePropose {
+    pub value: Proposal,
+    pub round: u64,
+}
+
+/// Ben-Or proposals are sent as strings: "0", "1" or "?".
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Proposal {
+    Value(u64),
+    Doubt,
+}
+
+impl TryFrom<String> for Proposal {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        match text.as_str() {
+            "?" => Ok(Proposal::Doubt),
+            _ => text.parse().map(Proposal::Value).map_err(|_| format!("invalid proposal {:?}", text)),
+        }
+    }
+}
+
+impl From<Proposal> for String {
+    fn from(proposal: Proposal) -> Self {
+        proposal.to_string()
+    }
+}
+
+impl fmt::Display for Proposal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proposal::Value(value) => write!(f, "{}", value),
+            Proposal::Doubt => write!(f, "?"),
+        }
+    }
+}
+
+impl ProtocolMessage for MessageInit {
+    const TYPE: &'static str = "INIT";
+}
+
+impl ProtocolMessage for MessageResult {
+    const TYPE: &'static str = "RESULT";
+}
+
+impl ProtocolMessage for MessageDelivery {
+    const TYPE: &'static str = "DELIVERY";
+}
+
+impl ProtocolMessage for MessageAccept {
+    const TYPE: &'static str = "ACCEPT";
+}
+
+impl ProtocolMessage for MessageEcho {
+    const TYPE: &'static str = "ECHO";
+}
+
+impl ProtocolMessage for MessageReady {
+    const TYPE: &'static str = "READY";
+}
+
+impl ProtocolMessage for MessageEst {
+    const TYPE: &'static str = "EST";
+}
+
+impl ProtocolMessage for MessageAux {
+    const TYPE: &'static str = "AUX";
+}
+
+impl ProtocolMessage for MessageCoordValue {
+    const TYPE: &'static str = "COORD_VALUE";
+}
+
+impl ProtocolMessage for MessageRbInit {
+    const TYPE: &'static str = "RB_INIT";
+}
+
+impl ProtocolMessage for MessageRbEcho {
+    const TYPE: &'static str = "RB_ECHO";
+}
+
+impl ProtocolMessage for MessageRbReady {
+    const TYPE: &'static str = "RB_READY";
+}
+
+impl ProtocolMessage for MessageVote {
+    const TYPE: &'static str = "VOTE";
+}
+
+impl ProtocolMessage for MessagePropose {
+    const TYPE: &'static str = "PROPOSE";
+}
@@ -0,0 +1,280 @@
+use std::collections::{ BTreeMap, BTreeSet, VecDeque };
+
+use dslib::pynode::JsonMessage;
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use super::events::{ self, Event };
+use super::messages::{ self, MessageAccept, MessageInit, MessageReady, MessageResult, ProtocolMessage };
+
+/// Safety invariant checked on the events of a run as they happen.
+pub trait Monitor {
+    fn name(&self) -> &str;
+
+    /// Description of the violation if `event` breaks the invariant.
+    fn check(&mut self, event: &Event) -> Option<String>;
+}
+
+/// Reliable broadcast: a correct node never sends READY for two different
+/// values and never ACCEPTs a value without 2f+1 READYs for it.
+pub struct RbMonitor {
+    correct_nodes: BTreeSet<String>,
+    quorum: usize,
+    /// Value of the READY messages sent by every correct node
+    ready_sent: BTreeMap<String, u64>,
+    /// Senders of the READY messages received by (node, value)
+    ready_received: BTreeMap<(String, u64), BTreeSet<String>>,
+}
+
+impl RbMonitor {
+    pub fn new(correct_nodes: &[String], faulty_count: u32) -> Self {
+        RbMonitor {
+            correct_nodes: correct_nodes.iter().cloned().collect(),
+            quorum: 2 * faulty_count as usize + 1,
+            ready_sent: BTreeMap::new(),
+            ready_received: BTreeMap::new(),
+        }
+    }
+}
+
+impl Monitor for RbMonitor {
+    fn name(&self) -> &str {
+        "reliable broadcast"
+    }
+
+    fn check(&mut self, event: &Event) -> Option<String> {
+        match event {
+            Event::MessageSend { src, msg, .. } if msg.tip == MessageReady::TYPE && self.correct_nodes.contains(src) => {
+                let value = match MessageReady::decode(msg) {
+                    Ok(ready) => ready.value,
+                    Err(e) => return Some(format!("Node {}: {}", src, e)),
+                };
+                let sent = self.ready_sent.entry(src.clone()).or_insert(value);
+                if *sent != value {
+                    return Some(format!("Node {} sent READY for {} and {}", src, sent, value));
+                }
+            }
+            Event::MessageReceive { src, dest, msg, .. } if msg.tip == MessageReady::TYPE => {
+                // a malformed READY of a Byzantine node counts for no value
+                if let Ok(ready) = MessageReady::decode(msg) {
+                    self.ready_received.entry((dest.clone(), ready.value)).or_default().insert(src.clone());
+                }
+            }
+            Event::LocalMessageSend { node, msg, .. } if msg.tip == MessageAccept::TYPE && self.correct_nodes.contains(node) => {
+                let value = match MessageAccept::decode(msg) {
+                    Ok(accept) => accept.value,
+                    Err(e) => return Some(format!("Node {}: {}", node, e)),
+                };
+                let readies = self.ready_received.get(&(node.clone(), value)).map_or(0, BTreeSet::len);
+                if readies < self.quorum {
+                    return Some(format!(
+                        "Node {} accepted {} after {} READY messages, expected at least {}",
+                        node, value, readies, self.quorum
+                    ));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Binary value broadcast: a correct node adds to `bin_values` (delivers)
+/// only values proposed by some correct node.
+pub struct BvMonitor {
+    correct_nodes: BTreeSet<String>,
+    proposed: BTreeSet<u64>,
+    deliver_type: String,
+}
+
+impl BvMonitor {
+    pub fn new(correct_nodes: &[String], deliver_type: &str) -> Self {
+        BvMonitor {
+            correct_nodes: correct_nodes.iter().cloned().collect(),
+            proposed: BTreeSet::new(),
+            deliver_type: deliver_type.to_string(),
+        }
+    }
+}
+
+impl Monitor for BvMonitor {
+    fn name(&self) -> &str {
+        "binary value broadcast"
+    }
+
+    fn check(&mut self, event: &Event) -> Option<String> {
+        match event {
+            Event::LocalMessageReceive { node, msg, .. } if msg.tip == MessageInit::TYPE && self.correct_nodes.contains(node) => {
+                if let Ok(init) = MessageInit::decode(msg) {
+                    self.proposed.insert(init.value);
+                }
+            }
+            Event::LocalMessageSend { node, msg, .. } if msg.tip == self.deliver_type && self.correct_nodes.contains(node) => {
+                let value = match messages::delivered_value(msg) {
+                    Ok(value) => value,
+                    Err(e) => return Some(format!("Node {}: {}", node, e)),
+                };
+                if !self.proposed.contains(&value) {
+                    return Some(format!("Node {} added {} to bin_values, but no correct node proposed it", node, value));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Consensus: the results of any two correct nodes are the same.
+pub struct AgreementMonitor {
+    correct_nodes: BTreeSet<String>,
+    /// First decision, (node, value)
+    decided: Option<(String, u64)>,
+}
+
+impl AgreementMonitor {
+    pub fn new(correct_nodes: &[String]) -> Self {
+        AgreementMonitor {
+            correct_nodes: correct_nodes.iter().cloned().collect(),
+            decided: None,
+        }
+    }
+}
+
+impl Monitor for AgreementMonitor {
+    fn name(&self) -> &str {
+        "agreement"
+    }
+
+    fn check(&mut self, event: &Event) -> Option<String> {
+        if let Event::LocalMessageSend { node, msg, .. } = event {
+            if msg.tip == MessageResult::TYPE && self.correct_nodes.contains(node) {
+                let value = match MessageResult::decode(msg) {
+                    Ok(result) => result.value,
+                    Err(e) => return Some(format!("Node {}: {}", node, e)),
+                };
+                let (first_node, first_value) = self.decided.get_or_insert_with(|| (node.clone(), value));
+                if *first_value != value {
+                    return Some(format!("Node {} decided {}, but node {} decided {}", node, value, first_node, first_value));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Node at which the event happens, `None` for network events.
+fn event_node(event: &Event) -> Option<&str> {
+    match event {
+        Event::MessageSend { src, .. } => Some(src),
+        Event::MessageReceive { dest, .. } => Some(dest),
+        Event::LocalMessageSend { node, .. }
+        | Event::LocalMessageReceive { node, .. }
+        | Event::TimerSet { node, .. }
+        | Event::TimerCancel { node, .. }
+        | Event::TimerFired { node, .. }
+        | Event::NodeCrashed { node, .. }
+        | Event::NodeDisconnected { node, .. }
+        | Event::NodeConnected { node, .. } => Some(node),
+        Event::MessageDrop { .. } | Event::Network { .. } => None,
+    }
+}
+
+type VectorClock = BTreeMap<String, u64>;
+
+/// Events which happen before the last one (in the sense of Lamport), with
+/// the last one itself. Deliveries are matched with the sends in FIFO order,
+/// a duplicate delivery with the last matched send.
+pub fn causal_prefix(events: &[Event]) -> Vec<&Event> {
+    let mut clocks: BTreeMap<String, VectorClock> = BTreeMap::new();
+    let mut in_flight: BTreeMap<(String, String, String, String), VecDeque<VectorClock>> = BTreeMap::new();
+    let mut delivered: BTreeMap<(String, String, String, String), VectorClock> = BTreeMap::new();
+    // node and its own clock component at every event
+    let mut stamps: Vec<Option<(String, u64)>> = Vec::new();
+
+    for event in events {
+        let node = match event_node(event) {
+            Some(node) => node.to_string(),
+            None => {
+                stamps.push(None);
+                continue;
+            }
+        };
+        let mut clock = clocks.remove(&node).unwrap_or_default();
+        if let Event::MessageReceive { src, dest, msg, .. } = event {
+            let key = (src.clone(), dest.clone(), msg.tip.clone(), msg.data.clone());
+            let sent = in_flight.get_mut(&key).and_then(VecDeque::pop_front).or_else(|| delivered.get(&key).cloned());
+            if let Some(sent) = sent {
+                for (other, time) in sent.iter() {
+                    let entry = clock.entry(other.clone()).or_insert(0);
+                    *entry = (*entry).max(*time);
+                }
+                delivered.insert(key, sent);
+            }
+        }
+        let own = clock.entry(node.clone()).or_insert(0);
+        *own += 1;
+        stamps.push(Some((node.clone(), *own)));
+        if let Event::MessageSend { src, dest, msg, .. } = event {
+            let key = (src.clone(), dest.clone(), msg.tip.clone(), msg.data.clone());
+            in_flight.entry(key).or_default().push_back(clock.clone());
+        }
+        clocks.insert(node, clock);
+    }
+
+    let last = match stamps.last() {
+        Some(Some((node, _))) => clocks.get(node).cloned().unwrap_or_default(),
+        _ => return events.last().into_iter().collect(),
+    };
+    events
+        .iter()
+        .zip(stamps.iter())
+        .filter(|(_, stamp)| stamp.as_ref().is_some_and(|(node, time)| last.get(node).is_some_and(|t| time <= t)))
+        .map(|(event, _)| event)
+        .collect()
+}
+
+/// Feeds the events of a run to a monitor. The system is stepped one event
+/// at a time, so that it stops right after the violating one.
+pub struct RunMonitor {
+    monitor: Box<dyn Monitor>,
+    /// Number of the recorded events already checked
+    checked: usize,
+}
+
+impl RunMonitor {
+    pub fn new(monitor: Box<dyn Monitor>) -> Self {
+        RunMonitor { monitor, checked: 0 }
+    }
+
+    /// Checks the events recorded since the previous call. On a violation
+    /// prints the causal prefix of the violating event and returns the error.
+    pub fn check(&mut self) -> TestResult {
+        let new_events = events::get_events_from(self.checked);
+        for (idx, event) in new_events.iter().enumerate() {
+            if let Some(violation) = self.monitor.check(event) {
+                let count = self.checked + idx + 1;
+                let run = events::get_events_from(0);
+                let prefix = causal_prefix(&run[..count]);
+                println!("monitor: {} violated at event {}: {}", self.monitor.name(), count, violation);
+                println!("causal prefix ({} of {} events):", prefix.len(), count);
+                for event in prefix {
+                    println!("{}", event);
+                }
+                self.checked = count;
+                return Err(format!("{} violated: {}", self.monitor.name(), violation));
+            }
+        }
+        self.checked += new_events.len();
+        Ok(true)
+    }
+
+    /// `System::step_until_no_events` which stops at the first violation.
+    pub fn step_until_no_events(&mut self, sys: &mut System<JsonMessage>) -> TestResult {
+        loop {
+            self.check()?;
+            if !sys.step() {
+                return Ok(true);
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sugars::{ rc, refcell };
+
+use dslib::node::{ Context, Node };
+use dslib::pynode::JsonMessage;
+use dslib::system::System;
+use dslib::test::TestResult;
+
+use super::{ NodeFactory, TestConfig, Validity };
+
+/// Timers of `PartitionedNode` start with this prefix, the inner node never sees them.
+const HEAL_TIMER: &str = "PARTITION-HEAL";
+
+/// Network partition in the partially synchronous model: from `start` to
+/// `end` messages between different groups are held back and delivered at
+/// `end`, instead of being dropped as with `System::make_partition`.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub groups: Vec<Vec<String>>,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Partition {
+    pub fn new(groups: &[Vec<u32>], start: f64, end: f64) -> Self {
+        Partition {
+            groups: groups.iter().map(|group| group.iter().map(|id| id.to_string()).collect()).collect(),
+            start,
+            end,
+        }
+    }
+
+    fn group_of(&self, node: &str) -> Option<usize> {
+        self.groups.iter().position(|group| group.iter().any(|id| id == node))
+    }
+
+    /// Whether a message from `src` to `dest` is held back at `time`.
+    /// Nodes outside every group stay connected to everyone.
+    pub fn separates(&self, src: &str, dest: &str, time: f64) -> bool {
+        if time < self.start || time >= self.end {
+            return false;
+        }
+        match (self.group_of(src), self.group_of(dest)) {
+            (Some(src_group), Some(dest_group)) => src_group != dest_group,
+            _ => false,
+        }
+    }
+}
+
+/// Wraps a node and holds back the messages it receives across a partition
+/// until the partition ends.
+pub struct PartitionedNode {
+    id: String,
+    inner: Rc<RefCell<dyn Node<JsonMessage>>>,
+    partitions: Rc<Vec<Partition>>,
+    /// Held messages with their senders, by partition
+    held: Vec<Vec<(JsonMessage, String)>>,
+}
+
+impl Node<JsonMessage> for PartitionedNode {
+    fn id(&self) -> &String {
+        &self.id
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        let time = ctx.time();
+        if let Some(idx) = self.partitions.iter().position(|p| p.separates(&from, &self.id, time)) {
+            if self.held[idx].is_empty() {
+                let end = self.partitions[idx].end;
+                ctx.set_timer(&format!("{}-NODE-{}|{}", HEAL_TIMER, self.id, idx), end - time);
+            }
+            self.held[idx].push((msg, from));
+            return;
+        }
+        self.inner.borrow_mut().on_message(msg, from, ctx);
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        self.inner.borrow_mut().on_local_message(msg, ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        if timer.starts_with(HEAL_TIMER) {
+            let idx: usize = timer.rsplit('|').next().unwrap().parse().unwrap();
+            for (msg, from) in std::mem::take(&mut self.held[idx]) {
+                self.on_message(msg, from, ctx);
+            }
+            return;
+        }
+        self.inner.borrow_mut().on_timer(timer, ctx);
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.inner.borrow_mut().max_size()
+    }
+}
+
+/// Builds nodes with `inner` and wraps them into `PartitionedNode`.
+pub struct PartitionedFactory<'a> {
+    inner: &'a dyn NodeFactory,
+    partitions: Rc<Vec<Partition>>,
+}
+
+impl<'a> PartitionedFactory<'a> {
+    pub fn new(inner: &'a dyn NodeFactory, partitions: Vec<Partition>) -> Self {
+        PartitionedFactory { inner, partitions: rc!(partitions) }
+    }
+}
+
+impl NodeFactory for PartitionedFactory<'_> {
+    fn build_node(
+        &self,
+        node_id: &str,
+        node_ids: &[String],
+        faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+        let inner = self.inner.build_node(node_id, node_ids, faulty_count, seed);
+        rc!(refcell!(PartitionedNode {
+            id: node_id.to_string(),
+            inner,
+            partitions: self.partitions.clone(),
+            held: vec![Vec::new(); self.partitions.len()],
+        }))
+    }
+}
+
+/// The last f nodes against the rest, the majority can still decide.
+pub fn minority_groups(node_count: u32, faulty_count: u32) -> Vec<Vec<u32>> {
+    let split = node_count - faulty_count;
+    vec![(0..split).collect(), (split..node_count).collect()]
+}
+
+/// Two halves, none of which has n - f nodes to make progress alone.
+pub fn split_groups(node_count: u32) -> Vec<Vec<u32>> {
+    let half = node_count / 2;
+    vec![(0..half).collect(), (half..node_count).collect()]
+}
+
+/// The coordinator of the given round against the rest.
+pub fn coordinator_groups(round: u32, node_count: u32) -> Vec<Vec<u32>> {
+    let coordinator = super::coordinator(round, node_count);
+    vec![vec![coordinator], (0..node_count).filter(|id| *id != coordinator).collect()]
+}
+
+/// Runs a consensus with the network partitioned into `groups` from the
+/// start until `heal_time`. Safety must hold for the decisions made while
+/// the network is partitioned, every node must decide after it heals.
+pub fn run_partition_test<F>(
+    config: &TestConfig,
+    groups: &[Vec<u32>],
+    heal_time: f64,
+    gen_init_values: F,
+    validity: Validity
+) -> TestResult
+where
+    F: FnOnce(&mut System<JsonMessage>) -> Vec<u64>
+{
+    let factory = PartitionedFactory::new(config.node_factory, vec![Partition::new(groups, 0.0, heal_time)]);
+    let mut partitioned_config = *config;
+    partitioned_config.node_factory = &factory;
+    let mut sys = super::build_system(&partitioned_config);
+    let nodes = sys.get_node_ids();
+
+    let init_values = gen_init_values(&mut sys);
+    let init_values = super::get_init_values(config, init_values);
+    super::send_init_messages(&mut sys, &init_values);
+
+    sys.step_for_duration(heal_time);
+    super::check_consensus_safety(&sys, &nodes, &init_values, validity)
+        .map_err(|e| format!("During partition {:?}: {}", groups, e))?;
+
+    sys.step_until_no_events();
+    super::check_consensus_properties(&mut sys, &nodes, &init_values, validity)
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+
+use serde::{ Deserialize, Serialize };
+
+use super::sweep::{ jobs_or_default, run_cells, SweepGrid, SweepRow };
+
+/// Distributions of one grid point, used as the reference for later runs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BaselineGroup {
+    pub failed_runs: usize,
+    pub avg_round: Vec<f64>,
+    pub latency_median: Vec<f64>,
+}
+
+/// Stored experiment: the sweep grid and the results it produced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    pub grid: SweepGrid,
+    /// Groups by `group_name`
+    pub groups: BTreeMap<String, BaselineGroup>,
+}
+
+impl Baseline {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Can't open baseline {}: {}", path, e))?;
+        serde_json::from_reader(file).map_err(|e| format!("Can't parse baseline {}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("Can't create baseline {}: {}", path, e))?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| e.to_string())
+    }
+}
+
+fn group_name(row: &SweepRow) -> String {
+    format!(
+        "{} n={} f={} {} delays={}..{} drop={}",
+        row.protocol, row.node_count, row.faulty_count, row.input, row.min_delay, row.max_delay, row.drop_rate
+    )
+}
+
+/// Runs the grid and collects the distributions of every grid point.
+/// Values are sorted by seed, so the result doesn't depend on the worker order.
+pub fn collect_groups(
+    grid: &SweepGrid,
+    worker_args: &[String],
+    jobs: usize
+) -> Result<BTreeMap<String, BaselineGroup>, String> {
+    let mut rows_by_group = BTreeMap::<String, Vec<SweepRow>>::new();
+    run_cells(grid, worker_args, jobs, |_, rows| {
+        for row in rows {
+            rows_by_group.entry(group_name(&row)).or_default().push(row);
+        }
+        Ok(())
+    })?;
+
+    let mut groups = BTreeMap::new();
+    for (name, mut rows) in rows_by_group {
+        rows.sort_by_key(|row| row.seed);
+        let group = BaselineGroup {
+            failed_runs: rows.iter().filter(|row| !row.passed).count(),
+            avg_round: rows.iter().filter_map(|row| row.avg_round).collect(),
+            latency_median: rows.iter().filter_map(|row| row.latency_median).collect(),
+        };
+        groups.insert(name, group);
+    }
+    Ok(groups)
+}
+
+/// Standard normal CDF, using the erf approximation 7.1.26 of Abramowitz and Stegun.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+/// One-sided Mann-Whitney U test: p-value of the hypothesis that `current`
+/// is stochastically greater than `reference` (normal approximation with
+/// tie and continuity corrections).
+pub fn mann_whitney_greater(current: &[f64], reference: &[f64]) -> f64 {
+    let n1 = current.len() as f64;
+    let n2 = reference.len() as f64;
+    if current.is_empty() || reference.is_empty() {
+        return 1.0;
+    }
+
+    let mut values: Vec<(f64, bool)> = current.iter().map(|&v| (v, true))
+        .chain(reference.iter().map(|&v| (v, false)))
+        .collect();
+    values.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // average ranks of tied values
+    let mut rank_sum = 0.0;
+    let mut tie_term = 0.0;
+    let mut start = 0;
+    while start < values.len() {
+        let mut end = start;
+        while end + 1 < values.len() && values[end + 1].0 == values[start].0 {
+            end += 1;
+        }
+        let rank = (start + end) as f64 / 2.0 + 1.0;
+        rank_sum += rank * values[start..=end].iter().filter(|v| v.1).count() as f64;
+        let ties = (end - start + 1) as f64;
+        tie_term += ties.powi(3) - ties;
+        start = end + 1;
+    }
+
+    let n = n1 + n2;
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = (u - mean - 0.5) / variance.sqrt();
+    1.0 - normal_cdf(z)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+/// Compares one metric: the current values regress if they are significantly
+/// greater than the baseline values increased by `tolerance`.
+fn compare_metric(
+    name: &str,
+    metric: &str,
+    current: &[f64],
+    baseline: &[f64],
+    tolerance: f64,
+    alpha: f64
+) -> bool {
+    let allowed: Vec<f64> = baseline.iter().map(|v| v * (1.0 + tolerance)).collect();
+    let p_value = mann_whitney_greater(current, &allowed);
+    let regressed = p_value < alpha;
+    let (baseline_mean, current_mean) = (mean(baseline), mean(current));
+    // a relative change from zero is meaningless
+    let change = if baseline_mean != 0.0 {
+        format!("{:+.1}%", (current_mean / baseline_mean - 1.0) * 100.0)
+    } else {
+        "-".to_string()
+    };
+    println!(
+        "regression: {} {} baseline={:.3} current={:.3} change={} p={:.4} {}",
+        name,
+        metric,
+        baseline_mean,
+        current_mean,
+        change,
+        p_value,
+        if regressed { "REGRESSION" } else { "OK" }
+    );
+    !regressed
+}
+
+/// Compares fresh results with the baseline, printing a line per metric.
+/// Returns the descriptions of the regressions.
+pub fn compare(
+    baseline: &Baseline,
+    current: &BTreeMap<String, BaselineGroup>,
+    tolerance: f64,
+    alpha: f64
+) -> Vec<String> {
+    let mut regressions = Vec::new();
+    for (name, group) in current.iter() {
+        let reference = match baseline.groups.get(name) {
+            Some(reference) => reference,
+            None => {
+                regressions.push(format!("{} is missing in the baseline", name));
+                continue;
+            }
+        };
+        if group.failed_runs > reference.failed_runs {
+            regressions.push(format!(
+                "{}: {} runs failed, {} in the baseline", name, group.failed_runs, reference.failed_runs
+            ));
+        }
+        if !compare_metric(name, "avg_round", &group.avg_round, &reference.avg_round, tolerance, alpha) {
+            regressions.push(format!("{}: avg_round regressed", name));
+        }
+        if !compare_metric(name, "latency", &group.latency_median, &reference.latency_median, tolerance, alpha) {
+            regressions.push(format!("{}: latency regressed", name));
+        }
+    }
+    for name in baseline.groups.keys().filter(|name| !current.contains_key(*name)) {
+        regressions.push(format!("{} from the baseline wasn't run", name));
+    }
+    regressions
+}
+
+/// Command line entry point: re-runs the experiment stored in the baseline
+/// file and exits with code 1 if the rounds or latencies regressed by more
+/// than `tolerance` (relative) with significance level `alpha`.
+pub fn run_and_exit(baseline_path: &str, impl_path: &str, jobs: Option<usize>, tolerance: f64, alpha: f64) -> ! {
+    let worker_args = vec!["--impl".to_string(), impl_path.to_string()];
+    let result = Baseline::load(baseline_path).and_then(|baseline| {
+        let current = collect_groups(&baseline.grid, &worker_args, jobs_or_default(jobs))?;
+        Ok(compare(&baseline, &current, tolerance, alpha))
+    });
+    match result {
+        Ok(regressions) if regressions.is_empty() => {
+            println!("No regressions against {}", baseline_path);
+            std::process::exit(0);
+        }
+        Ok(regressions) => {
+            for regression in regressions {
+                eprintln!("{}", regression);
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Regression check failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Command line entry point: runs the sweep grid and stores it with its
+/// results as a new baseline.
+pub fn write_baseline_and_exit(grid_path: &str, baseline_path: &str, impl_path: &str, jobs: Option<usize>) -> ! {
+    let worker_args = vec!["--impl".to_string(), impl_path.to_string()];
+    let result = SweepGrid::load(grid_path).and_then(|grid| {
+        let groups = collect_groups(&grid, &worker_args, jobs_or_default(jobs))?;
+        Baseline { grid, groups }.save(baseline_path)
+    });
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("Can't write baseline: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
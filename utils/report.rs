@@ -0,0 +1,207 @@
+use std::cell::RefCell;
+use std::fs::{ self, File };
+use std::time::Instant;
+
+use serde::Serialize;
+
+use dslib::test::TestResult;
+
+use super::{ events, TestConfig };
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Json,
+    JUnit,
+}
+
+/// Outcome of a test run.
+#[derive(Clone, Debug, Serialize)]
+struct TestReport {
+    name: String,
+    node_count: u32,
+    faulty_count: u32,
+    seed: u64,
+    passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure: Option<String>,
+    /// Simulated time summed over the systems built by the test
+    sim_time: f64,
+    /// Events of these systems, see `events::Event`
+    events: usize,
+    /// Seconds
+    wall_time: f64,
+}
+
+#[derive(Serialize)]
+struct SuiteReport<'a> {
+    suite: &'a str,
+    passed: usize,
+    failed: usize,
+    tests: &'a [TestReport],
+}
+
+struct Reporter {
+    path: String,
+    format: Format,
+    tests: Vec<TestReport>,
+    /// Start of the current test
+    started: Instant,
+    /// Whether the current test has built a system, whose events are not
+    /// counted in `sim_time` and `events` yet
+    in_run: bool,
+    sim_time: f64,
+    events: usize,
+}
+
+impl Reporter {
+    /// Adds the events of the system built last to the current test.
+    fn count_run(&mut self) {
+        if self.in_run {
+            self.sim_time += events::get_events().last().map_or(0.0, |e| e.time());
+            self.events += events::event_count();
+        }
+    }
+}
+
+thread_local! {
+    static REPORTER: RefCell<Option<Reporter>> = const { RefCell::new(None) };
+}
+
+fn with_reporter(f: impl FnOnce(&mut Reporter)) {
+    REPORTER.with(|reporter| {
+        if let Some(reporter) = reporter.borrow_mut().as_mut() {
+            f(reporter);
+        }
+    });
+}
+
+/// Starts collecting the results of the tests for a report written to `path`
+/// by `finish`: JUnit XML for *.xml, JSON otherwise. Exits if the file can't
+/// be created.
+pub fn start(path: &str) {
+    if let Err(e) = File::create(path) {
+        eprintln!("Can't create the report {}: {}", path, e);
+        std::process::exit(2);
+    }
+    let format = if path.ends_with(".xml") { Format::JUnit } else { Format::Json };
+    let reporter = Reporter {
+        path: path.to_string(),
+        format,
+        tests: Vec::new(),
+        started: Instant::now(),
+        in_run: false,
+        sim_time: 0.0,
+        events: 0,
+    };
+    REPORTER.with(|r| *r.borrow_mut() = Some(reporter));
+}
+
+pub fn begin_test() {
+    with_reporter(|reporter| {
+        reporter.started = Instant::now();
+        reporter.in_run = false;
+        reporter.sim_time = 0.0;
+        reporter.events = 0;
+    });
+}
+
+/// Called for every built system, before the events of the previous one are cleared.
+pub(super) fn begin_run() {
+    with_reporter(|reporter| {
+        reporter.count_run();
+        reporter.in_run = true;
+    });
+}
+
+pub fn end_test(name: &str, config: &TestConfig, result: &TestResult) {
+    with_reporter(|reporter| {
+        reporter.count_run();
+        reporter.in_run = false;
+        reporter.tests.push(TestReport {
+            name: name.to_string(),
+            node_count: config.node_count,
+            faulty_count: config.faulty_count,
+            seed: config.seed,
+            passed: result.is_ok(),
+            failure: result.as_ref().err().cloned(),
+            sim_time: reporter.sim_time,
+            events: reporter.events,
+            wall_time: reporter.started.elapsed().as_secs_f64(),
+        });
+    });
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            '\n' => result.push_str("&#10;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn junit(suite: &str, tests: &[TestReport]) -> String {
+    let failures = tests.iter().filter(|test| !test.passed).count();
+    let time: f64 = tests.iter().map(|test| test.wall_time).sum();
+    let mut result = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    result += &format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        escape_xml(suite), tests.len(), failures, time
+    );
+    for test in tests {
+        result += &format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">\n",
+            escape_xml(&test.name), escape_xml(suite), test.wall_time
+        );
+        result += "      <properties>\n";
+        let properties = [
+            ("n", test.node_count.to_string()),
+            ("f", test.faulty_count.to_string()),
+            ("seed", test.seed.to_string()),
+            ("sim_time", format!("{:.3}", test.sim_time)),
+            ("events", test.events.to_string()),
+        ];
+        for (name, value) in properties {
+            result += &format!("        <property name=\"{}\" value=\"{}\"/>\n", name, value);
+        }
+        result += "      </properties>\n";
+        if let Some(failure) = &test.failure {
+            let message = escape_xml(failure);
+            result += &format!("      <failure message=\"{}\">{}</failure>\n", message, message);
+        }
+        result += "    </testcase>\n";
+    }
+    result += "  </testsuite>\n</testsuites>\n";
+    result
+}
+
+/// Writes the report of the tests run so far. Exits if it can't be written.
+pub fn finish() {
+    with_reporter(|reporter| {
+        let suite = env!("CARGO_PKG_NAME");
+        let text = match reporter.format {
+            Format::JUnit => junit(suite, &reporter.tests),
+            Format::Json => {
+                let failed = reporter.tests.iter().filter(|test| !test.passed).count();
+                let report = SuiteReport {
+                    suite,
+                    passed: reporter.tests.len() - failed,
+                    failed,
+                    tests: &reporter.tests,
+                };
+                serde_json::to_string_pretty(&report).unwrap() + "\n"
+            }
+        };
+        if let Err(e) = fs::write(&reporter.path, text) {
+            eprintln!("Can't write the report {}: {}", reporter.path, e);
+            std::process::exit(2);
+        }
+    });
+}
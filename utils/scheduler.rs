@@ -0,0 +1,306 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use sugars::{ rc, refcell };
+
+use dslib::node::{ Context, Node };
+use dslib::pynode::JsonMessage;
+use dslib::system::System;
+
+use super::messages::{
+    MessageAux, MessageCoordValue, MessageEst, MessageFields, MessagePropose, MessageVote, Proposal, ProtocolMessage
+};
+use super::{ ConsensusProperty, NodeFactory };
+
+/// Local message which makes `ScheduledNode` process the delivery chosen by the scheduler.
+const DELIVER: &str = "SCHEDULER-DELIVER";
+
+/// Message which has reached its destination and waits for the scheduler.
+#[derive(Clone, Debug)]
+pub struct Pending {
+    /// Arrival number, the same for the same message when a schedule is replayed
+    pub seq: u64,
+    pub from: String,
+    pub to: String,
+    pub msg: JsonMessage,
+}
+
+impl Pending {
+    pub fn round(&self) -> Option<u64> {
+        MessageFields::of(&self.msg).round
+    }
+
+    /// Binary value carried by the message: `value` of EST, COORD_VALUE and VOTE (0 or 1)
+    /// and of PROPOSE ("0" or "1"), the single value of `bin_values` of AUX.
+    /// `None` for messages which support both values or none.
+    pub fn binary_value(&self) -> Option<u64> {
+        match self.msg.tip.as_str() {
+            MessageEst::TYPE => MessageEst::decode(&self.msg).ok().map(|est| est.value),
+            MessageCoordValue::TYPE => MessageCoordValue::decode(&self.msg).ok().map(|coord_value| coord_value.value),
+            MessageVote::TYPE => MessageVote::decode(&self.msg).ok().map(|vote| vote.value),
+            MessagePropose::TYPE => match MessagePropose::decode(&self.msg).ok()?.value {
+                Proposal::Value(value) => Some(value),
+                Proposal::Doubt => None,
+            },
+            MessageAux::TYPE => match MessageAux::decode(&self.msg).ok()?.bin_values.as_slice() {
+                [value] => Some(*value),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Chooses which of the pending messages is delivered next.
+pub trait Scheduler {
+    fn name(&self) -> &str;
+
+    /// Index in `pending`, which is never empty, or `None` to stop the run.
+    /// Messages are in the order of their arrival.
+    fn next(&mut self, pending: &[Pending]) -> Option<usize>;
+}
+
+/// Delivers messages in the order of their arrival.
+#[derive(Default)]
+pub struct FifoScheduler;
+
+impl Scheduler for FifoScheduler {
+    fn name(&self) -> &str {
+        "fifo"
+    }
+
+    fn next(&mut self, _pending: &[Pending]) -> Option<usize> {
+        Some(0)
+    }
+}
+
+/// Keeps the values seen by every node balanced: for every receiver, message
+/// type and round it delivers the value the receiver has seen less of, so
+/// that no value gets a clear majority. Messages without a binary value go
+/// first, ties are broken by the arrival order.
+#[derive(Default)]
+pub struct SplitVoteScheduler {
+    /// Delivered messages by (receiver, type, round) and value
+    delivered: BTreeMap<(String, String, Option<u64>), [u64; 2]>,
+}
+
+impl SplitVoteScheduler {
+    /// How unbalanced the receiver's view gets if the message is delivered
+    fn imbalance(&self, pending: &Pending) -> u64 {
+        let value = match pending.binary_value() {
+            Some(value) if value < 2 => value as usize,
+            _ => return 0,
+        };
+        let key = (pending.to.clone(), pending.msg.tip.clone(), pending.round());
+        let mut counts = self.delivered.get(&key).copied().unwrap_or_default();
+        counts[value] += 1;
+        counts[value].abs_diff(counts[1 - value])
+    }
+}
+
+impl Scheduler for SplitVoteScheduler {
+    fn name(&self) -> &str {
+        "split-vote"
+    }
+
+    fn next(&mut self, pending: &[Pending]) -> Option<usize> {
+        let idx = (0..pending.len()).min_by_key(|&idx| self.imbalance(&pending[idx]))?;
+        let chosen = &pending[idx];
+        if let Some(value) = chosen.binary_value().filter(|value| *value < 2) {
+            let key = (chosen.to.clone(), chosen.msg.tip.clone(), chosen.round());
+            self.delivered.entry(key).or_default()[value as usize] += 1;
+        }
+        Some(idx)
+    }
+}
+
+/// Messages held by the wrappers of a system, shared with `run_scheduled`.
+#[derive(Default)]
+pub struct SchedulerState {
+    pending: Vec<Pending>,
+    /// Chosen delivery, taken by the receiver on `DELIVER`
+    next: Option<Pending>,
+    next_seq: u64,
+}
+
+/// Wraps a node and hands the messages it receives from other nodes over to
+/// the scheduler instead of processing them on arrival.
+pub struct ScheduledNode {
+    id: String,
+    inner: Rc<RefCell<dyn Node<JsonMessage>>>,
+    state: Rc<RefCell<SchedulerState>>,
+}
+
+impl Node<JsonMessage> for ScheduledNode {
+    fn id(&self) -> &String {
+        &self.id
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        if from == self.id {
+            self.inner.borrow_mut().on_message(msg, from, ctx);
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.pending.push(Pending { seq, from, to: self.id.clone(), msg });
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        if msg.tip == DELIVER {
+            let next = self.state.borrow_mut().next.take();
+            if let Some(Pending { from, msg, .. }) = next {
+                self.inner.borrow_mut().on_message(msg, from, ctx);
+            }
+            return;
+        }
+        self.inner.borrow_mut().on_local_message(msg, ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        self.inner.borrow_mut().on_timer(timer, ctx);
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.inner.borrow_mut().max_size()
+    }
+}
+
+/// Builds nodes with `inner` and wraps them into `ScheduledNode`.
+pub struct ScheduledFactory<'a> {
+    inner: &'a dyn NodeFactory,
+    state: Rc<RefCell<SchedulerState>>,
+}
+
+impl<'a> ScheduledFactory<'a> {
+    pub fn new(inner: &'a dyn NodeFactory) -> Self {
+        ScheduledFactory { inner, state: rc!(refcell!(SchedulerState::default())) }
+    }
+
+    /// Wraps the nodes of `inner` into the same schedule, e.g. the Byzantine ones.
+    pub fn share<'b>(&self, inner: &'b dyn NodeFactory) -> ScheduledFactory<'b> {
+        ScheduledFactory { inner, state: self.state.clone() }
+    }
+}
+
+impl NodeFactory for ScheduledFactory<'_> {
+    fn build_node(
+        &self,
+        node_id: &str,
+        node_ids: &[String],
+        faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+        let inner = self.inner.build_node(node_id, node_ids, faulty_count, seed);
+        rc!(refcell!(ScheduledNode { id: node_id.to_string(), inner, state: self.state.clone() }))
+    }
+}
+
+/// Outcome of `run_scheduled`.
+#[derive(Clone, Debug)]
+pub struct ScheduledRun {
+    pub deliveries: u64,
+    /// Highest round among the delivered messages
+    pub max_round: u64,
+    /// Whether the run stopped at `max_round` with messages still pending
+    pub cut: bool,
+}
+
+/// Runs the system built with `factory`, letting `scheduler` pick every
+/// delivery between nodes: after each one the system is stepped until it
+/// has no events, then the next message is chosen among all pending ones.
+/// Stops when nothing is pending, the scheduler stops it, a message of a round past `max_round`
+/// would be delivered or, with `until_decided`, every node has decided
+/// (sent a local message).
+pub fn run_scheduled(
+    sys: &mut System<JsonMessage>,
+    factory: &ScheduledFactory,
+    scheduler: &mut dyn Scheduler,
+    max_round: u64,
+    until_decided: bool
+) -> ScheduledRun {
+    let mut run = ScheduledRun { deliveries: 0, max_round: 0, cut: false };
+    let nodes = sys.get_node_ids();
+    loop {
+        sys.step_until_no_events();
+        // protocols like SafeBBC never stop on their own
+        if until_decided && nodes.iter().all(|node| !super::get_local_messages(sys, node).is_empty()) {
+            return run;
+        }
+        let mut state = factory.state.borrow_mut();
+        state.pending.retain(|pending| !sys.node_is_crashed(&pending.to));
+        if state.pending.is_empty() {
+            return run;
+        }
+        let idx = match scheduler.next(&state.pending) {
+            Some(idx) => idx,
+            None => return run,
+        };
+        let next = state.pending.remove(idx);
+        let round = next.round().unwrap_or(0);
+        if round > max_round {
+            run.cut = true;
+            return run;
+        }
+        run.max_round = run.max_round.max(round);
+        run.deliveries += 1;
+        let to = next.to.clone();
+        state.next = Some(next);
+        drop(state);
+        sys.send_local(JsonMessage::new(DELIVER, "{}"), &to);
+    }
+}
+
+/// Runs a consensus with alternating init values under `scheduler`, up to
+/// `max_round`, and prints the rounds it reached. Safety is always checked,
+/// termination only with `check_termination`, violations of the `ignored`
+/// properties aren't reported.
+pub fn run_scheduler_test(
+    config: &super::TestConfig,
+    scheduler: &mut dyn Scheduler,
+    max_round: u64,
+    check_termination: bool,
+    ignored: &[ConsensusProperty]
+) -> dslib::test::TestResult {
+    let factory = ScheduledFactory::new(config.node_factory);
+    let mut scheduled_config = *config;
+    scheduled_config.node_factory = &factory;
+    let mut sys = super::build_system(&scheduled_config);
+    let nodes = sys.get_node_ids();
+
+    let init_values: Vec<u64> = (0..nodes.len() as u64).map(|idx| idx % 2).collect();
+    let init_values = super::get_init_values(config, init_values);
+    super::send_init_messages(&mut sys, &init_values);
+
+    let run = run_scheduled(&mut sys, &factory, scheduler, max_round, true);
+    let decided = nodes.iter().filter(|node| !super::get_local_messages(&sys, node).is_empty()).count();
+    println!(
+        "scheduler: {} deliveries={} max_round={}{} decided={}/{}",
+        scheduler.name(),
+        run.deliveries,
+        run.max_round,
+        if run.cut { " (cut)" } else { "" },
+        decided,
+        nodes.len()
+    );
+
+    let mut violations = if check_termination {
+        super::get_consensus_violations(&mut sys, &nodes, &init_values, super::Validity::Binary)
+    } else {
+        super::evaluate_consensus(&sys, &nodes, &init_values, super::Validity::Binary)
+    };
+    violations.retain(|(property, _)| {
+        !ignored.contains(property) && (check_termination || *property != ConsensusProperty::Termination)
+    });
+    if violations.is_empty() {
+        return Ok(true);
+    }
+    super::print_diagnostics(&sys);
+    Err(violations
+        .iter()
+        .map(|(property, details)| format!("{:?} violated: {}", property, details))
+        .collect::<Vec<_>>()
+        .join("; "))
+}
@@ -0,0 +1,250 @@
+use super::fuzz::{ repro_command, run_catching, TestFn };
+use super::{ get_run_record, matrix, ByzPlacement, RunOverrides, RunRecord, TestConfig };
+
+/// Number of seeds tried for every smaller (n, f), starting from the current one.
+const SEED_ATTEMPTS: u64 = 5;
+
+/// Failing scenario found by `shrink`.
+#[derive(Clone, Debug)]
+pub struct Shrunk {
+    pub node_count: u32,
+    pub faulty_count: u32,
+    pub seed: u64,
+    pub overrides: RunOverrides,
+    /// Message delays of the test, which a suite config may have set
+    pub delays: Option<(f64, f64)>,
+    pub error: String,
+    /// Number of test runs made by the search
+    pub runs: usize,
+}
+
+impl Shrunk {
+    /// Command line which reruns the shrunk scenario. An instance of a suite
+    /// config is rerun as the test it was expanded from, without the config.
+    pub fn repro_command(&self, test: &str) -> String {
+        let mut args = vec![
+            ("--nodes", Some('n'), self.node_count.to_string()),
+            ("--faulty_count", Some('f'), self.faulty_count.to_string()),
+            ("--seed", Some('s'), self.seed.to_string()),
+            ("--test", Some('t'), matrix::base_name(test).to_string()),
+            ("--config", None, String::new()),
+        ];
+        if let Some((min, max)) = self.delays {
+            args.push(("--delays", None, format!("{}..{}", min, max)));
+        }
+        if !self.overrides.is_empty() {
+            args.push(("--overrides", None, serde_json::to_string(&self.overrides).unwrap()));
+        }
+        repro_command(&args)
+    }
+}
+
+/// Kind of a failure, its message up to the first colon without numbers,
+/// e.g. "Agreement violated" or "Node #". Shrinking keeps the kind, so that
+/// the search doesn't drift to an unrelated failure.
+fn failure_kind(error: &str) -> String {
+    let head = error.split(':').next().unwrap_or_default();
+    let mut kind = String::new();
+    for c in head.chars() {
+        if c.is_ascii_digit() {
+            if !kind.ends_with('#') {
+                kind.push('#');
+            }
+        } else {
+            kind.push(c);
+        }
+    }
+    kind
+}
+
+/// Largest k in {3, 2} such that n > k * f holds for the original scenario,
+/// smaller scenarios keep this resilience so they stay meaningful.
+fn resilience(node_count: u32, faulty_count: u32) -> u32 {
+    [3, 2].into_iter().find(|k| node_count > k * faulty_count).unwrap_or(0)
+}
+
+/// Values from `min` up to `current - 1`, smallest first: min, then halfway
+/// from `current` to min, a quarter of the way, and so on.
+fn smaller_values(current: u32, min: u32) -> Vec<u32> {
+    let mut values = Vec::new();
+    if current <= min {
+        return values;
+    }
+    let mut step = current - min;
+    while step > 0 {
+        values.push(current - step);
+        step /= 2;
+    }
+    values.dedup();
+    values
+}
+
+/// Byzantine placements count nodes explicitly, so they are fitted into the smaller scenario.
+fn fit_byz_placement(placement: ByzPlacement, faulty_count: u32) -> ByzPlacement {
+    match placement {
+        ByzPlacement::First(k) => ByzPlacement::First(k.min(faulty_count)),
+        ByzPlacement::Last(k) => ByzPlacement::Last(k.min(faulty_count)),
+        ByzPlacement::Random(k) => ByzPlacement::Random(k.min(faulty_count)),
+        ByzPlacement::Coordinators(k) => ByzPlacement::Coordinators(k.min(faulty_count)),
+        placement => placement,
+    }
+}
+
+struct Search<'a, 'b> {
+    func: TestFn,
+    base: &'b TestConfig<'a>,
+    kind: String,
+    best: Shrunk,
+    record: RunRecord,
+}
+
+impl Search<'_, '_> {
+    /// Runs the candidate and makes it the best one if it fails in the same way.
+    fn try_candidate(&mut self, node_count: u32, faulty_count: u32, seed: u64, overrides: RunOverrides) -> bool {
+        let mut config = *self.base;
+        config.node_count = node_count;
+        config.faulty_count = faulty_count;
+        config.byz_placement = fit_byz_placement(config.byz_placement, faulty_count);
+        config.seed = seed;
+        config.overrides = Some(&overrides);
+
+        self.best.runs += 1;
+        match run_catching(self.func, &config) {
+            Err(error) if failure_kind(&error) == self.kind => {
+                self.record = get_run_record();
+                let runs = self.best.runs;
+                let delays = self.base.network.delays;
+                self.best = Shrunk { node_count, faulty_count, seed, overrides, delays, error, runs };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn shrink_node_count(&mut self, resilience: u32) -> bool {
+        let min = (resilience * self.best.faulty_count + 1).max(2);
+        for node_count in smaller_values(self.best.node_count, min) {
+            for seed in self.best.seed..self.best.seed + SEED_ATTEMPTS {
+                // init values are generated anew for the other node count
+                let overrides = RunOverrides { init_values: None, ..self.best.overrides.clone() };
+                if self.try_candidate(node_count, self.best.faulty_count, seed, overrides) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn shrink_faulty_count(&mut self) -> bool {
+        for faulty_count in smaller_values(self.best.faulty_count, 0) {
+            for seed in self.best.seed..self.best.seed + SEED_ATTEMPTS {
+                // faults are numbered anew for the other faulty count
+                let overrides = RunOverrides {
+                    init_values: self.best.overrides.init_values.clone(),
+                    ..RunOverrides::default()
+                };
+                if self.try_candidate(self.best.node_count, faulty_count, seed, overrides) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn shrink_faults(&mut self) -> bool {
+        for fault in 0..self.record.fault_count {
+            let overrides = &self.best.overrides;
+            if overrides.skipped_faults.contains(&fault) {
+                continue;
+            }
+            let mut skipped = overrides.clone();
+            skipped.skipped_faults.push(fault);
+            skipped.skipped_faults.sort_unstable();
+            if self.try_candidate(self.best.node_count, self.best.faulty_count, self.best.seed, skipped) {
+                return true;
+            }
+
+            let overrides = &self.best.overrides;
+            if overrides.early_faults.contains(&fault) {
+                continue;
+            }
+            let mut early = overrides.clone();
+            early.early_faults.push(fault);
+            early.early_faults.sort_unstable();
+            if self.try_candidate(self.best.node_count, self.best.faulty_count, self.best.seed, early) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Replaces init values with the smallest one, first all at once, then one by one.
+    fn shrink_init_values(&mut self) -> bool {
+        let values = self.record.init_values.clone();
+        let min = match values.iter().min() {
+            Some(&min) => min,
+            None => return false,
+        };
+        let mut candidates = Vec::new();
+        if values.iter().any(|&v| v != min) {
+            candidates.push(vec![min; values.len()]);
+        }
+        for (idx, &value) in values.iter().enumerate() {
+            if value != min {
+                let mut candidate = values.clone();
+                candidate[idx] = min;
+                candidates.push(candidate);
+            }
+        }
+        for candidate in candidates {
+            let overrides = RunOverrides { init_values: Some(candidate), ..self.best.overrides.clone() };
+            if self.try_candidate(self.best.node_count, self.best.faulty_count, self.best.seed, overrides) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Searches for a smaller scenario in which the test fails the same way as
+/// with `config`: fewer nodes and faulty nodes, fewer or earlier faults and
+/// simpler init values. Greedily takes every smaller failing scenario until
+/// none is found. Returns `None` if the test doesn't fail with `config`.
+pub fn shrink(func: TestFn, config: &TestConfig) -> Option<Shrunk> {
+    // every failing candidate would print its diagnostics
+    super::events::set_quiet(true);
+    let shrunk = find_smaller(func, config);
+    super::events::set_quiet(false);
+    shrunk
+}
+
+fn find_smaller(func: TestFn, config: &TestConfig) -> Option<Shrunk> {
+    let overrides = config.overrides.cloned().unwrap_or_default();
+    let mut config = *config;
+    config.overrides = Some(&overrides);
+
+    let error = run_catching(func, &config).err()?;
+    let mut search = Search {
+        func,
+        base: &config,
+        kind: failure_kind(&error),
+        best: Shrunk {
+            node_count: config.node_count,
+            faulty_count: config.faulty_count,
+            seed: config.seed,
+            overrides: overrides.clone(),
+            delays: config.network.delays,
+            error,
+            runs: 1,
+        },
+        record: get_run_record(),
+    };
+
+    let resilience = resilience(config.node_count, config.faulty_count);
+    while search.shrink_node_count(resilience)
+        || search.shrink_faulty_count()
+        || search.shrink_faults()
+        || search.shrink_init_values()
+    {}
+    Some(search.best)
+}
@@ -0,0 +1,459 @@
+use std::fs::File;
+use std::io::{ self, BufRead, BufReader, Write };
+use std::process::{ Command, Stdio };
+use std::sync::{ mpsc, Mutex };
+use std::thread;
+
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+use serde::{ Deserialize, Serialize };
+
+use dslib::pynode::PyNodeFactory;
+
+use super::messages::{ MessageResult, ProtocolMessage };
+use super::{
+    build_system, evaluate_consensus, get_decision_latencies, get_local_messages,
+    get_message_counter, send_init_messages, ByzPlacement, LatencyStats, NetworkConfig, TestConfig, Validity
+};
+
+/// Prefix of the stdout lines which carry rows from a worker to the parent.
+const ROW_PREFIX: &str = "sweep-row: ";
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputDistribution {
+    /// The given percentage of nodes (rounded up) propose 1, the rest propose 0,
+    /// shuffled with the run seed
+    PercentageOfOnes(u64),
+    /// Every node proposes 0 or 1 uniformly at random
+    RandomBinary,
+    /// Node `i` proposes `i`, for multi-valued consensus
+    AllDistinct,
+}
+
+impl InputDistribution {
+    pub fn generate(&self, node_count: u32, seed: u64) -> Vec<u64> {
+        let n = node_count as usize;
+        let mut rand = Pcg64::seed_from_u64(seed);
+        match self {
+            InputDistribution::PercentageOfOnes(percentage) => {
+                let ones = (percentage * n as u64).div_ceil(100).min(n as u64) as usize;
+                let mut init_values = vec![0; n];
+                init_values[..ones].fill(1);
+                init_values.shuffle(&mut rand);
+                init_values
+            }
+            InputDistribution::RandomBinary => (0..n).map(|_| rand.gen_range(0..2)).collect(),
+            InputDistribution::AllDistinct => (0..n as u64).collect(),
+        }
+    }
+
+    pub fn validity(&self) -> Validity {
+        match self {
+            InputDistribution::AllDistinct => Validity::MultiValued,
+            _ => Validity::Binary,
+        }
+    }
+}
+
+impl std::fmt::Display for InputDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputDistribution::PercentageOfOnes(percentage) => write!(f, "ones{}", percentage),
+            InputDistribution::RandomBinary => write!(f, "random_binary"),
+            InputDistribution::AllDistinct => write!(f, "all_distinct"),
+        }
+    }
+}
+
+fn default_delays() -> Vec<(f64, f64)> {
+    vec![(1.0, 1.0)]
+}
+
+fn default_drop_rates() -> Vec<f64> {
+    vec![0.0]
+}
+
+fn default_max_time() -> f64 {
+    10000.0
+}
+
+fn default_seeds_per_worker() -> u64 {
+    50
+}
+
+/// Parameter grid of a sweep, every combination is run for every seed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepGrid {
+    /// Node classes in the implementation file
+    pub protocols: Vec<String>,
+    pub node_counts: Vec<u32>,
+    pub faulty_counts: Vec<u32>,
+    pub inputs: Vec<InputDistribution>,
+    /// (min, max) network delays
+    #[serde(default = "default_delays")]
+    pub delays: Vec<(f64, f64)>,
+    #[serde(default = "default_drop_rates")]
+    pub drop_rates: Vec<f64>,
+    /// Inclusive range of seeds
+    pub seeds: (u64, u64),
+    /// Simulated time after which a run is stopped, if not every node decided
+    #[serde(default = "default_max_time")]
+    pub max_time: f64,
+    #[serde(default = "default_seeds_per_worker")]
+    pub seeds_per_worker: u64,
+}
+
+impl SweepGrid {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Can't open sweep grid {}: {}", path, e))?;
+        serde_json::from_reader(file).map_err(|e| format!("Can't parse sweep grid {}: {}", path, e))
+    }
+
+    /// Splits the grid into independent cells, each covering a chunk of seeds.
+    pub fn cells(&self) -> Vec<SweepCell> {
+        let mut cells = Vec::new();
+        let chunk = self.seeds_per_worker.max(1);
+        for protocol in self.protocols.iter() {
+            for &node_count in self.node_counts.iter() {
+                for &faulty_count in self.faulty_counts.iter() {
+                    for &input in self.inputs.iter() {
+                        for &(min_delay, max_delay) in self.delays.iter() {
+                            for &drop_rate in self.drop_rates.iter() {
+                                let mut first_seed = self.seeds.0;
+                                while first_seed <= self.seeds.1 {
+                                    let last_seed = (first_seed + chunk - 1).min(self.seeds.1);
+                                    cells.push(SweepCell {
+                                        protocol: protocol.clone(),
+                                        node_count,
+                                        faulty_count,
+                                        input,
+                                        min_delay,
+                                        max_delay,
+                                        drop_rate,
+                                        seeds: (first_seed, last_seed),
+                                        max_time: self.max_time,
+                                    });
+                                    first_seed = last_seed + 1;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        cells
+    }
+}
+
+/// Unit of work of a single worker process.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepCell {
+    pub protocol: String,
+    pub node_count: u32,
+    pub faulty_count: u32,
+    pub input: InputDistribution,
+    pub min_delay: f64,
+    pub max_delay: f64,
+    pub drop_rate: f64,
+    pub seeds: (u64, u64),
+    pub max_time: f64,
+}
+
+/// Result of a single run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepRow {
+    pub protocol: String,
+    pub node_count: u32,
+    pub faulty_count: u32,
+    pub input: String,
+    pub min_delay: f64,
+    pub max_delay: f64,
+    pub drop_rate: f64,
+    pub seed: u64,
+    pub passed: bool,
+    pub error: String,
+    pub decided_value: Option<u64>,
+    pub avg_round: Option<f64>,
+    pub max_round: Option<u64>,
+    pub latency_min: Option<f64>,
+    pub latency_median: Option<f64>,
+    pub latency_max: Option<f64>,
+    pub latency_p99: Option<f64>,
+    pub messages: u64,
+    pub sim_time: f64,
+}
+
+const CSV_HEADER: &str = "protocol,node_count,faulty_count,input,min_delay,max_delay,drop_rate,seed,\
+passed,error,decided_value,avg_round,max_round,latency_min,latency_median,latency_max,latency_p99,\
+messages,sim_time";
+
+fn csv_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or(String::new(), |v| v.to_string())
+}
+
+fn csv_string(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl SweepRow {
+    pub fn to_csv(&self) -> String {
+        [
+            csv_string(&self.protocol),
+            self.node_count.to_string(),
+            self.faulty_count.to_string(),
+            csv_string(&self.input),
+            self.min_delay.to_string(),
+            self.max_delay.to_string(),
+            self.drop_rate.to_string(),
+            self.seed.to_string(),
+            self.passed.to_string(),
+            csv_string(&self.error),
+            csv_option(self.decided_value),
+            csv_option(self.avg_round),
+            csv_option(self.max_round),
+            csv_option(self.latency_min),
+            csv_option(self.latency_median),
+            csv_option(self.latency_max),
+            csv_option(self.latency_p99),
+            self.messages.to_string(),
+            self.sim_time.to_string(),
+        ].join(",")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    Csv,
+    JsonLines,
+}
+
+impl OutputFormat {
+    /// CSV for `*.csv` paths, JSON lines otherwise.
+    pub fn from_path(path: Option<&str>) -> Self {
+        match path {
+            Some(path) if path.ends_with(".csv") => OutputFormat::Csv,
+            _ => OutputFormat::JsonLines,
+        }
+    }
+}
+
+fn run_seed(cell: &SweepCell, factory: &PyNodeFactory, seed: u64) -> SweepRow {
+    let config = TestConfig {
+        node_count: cell.node_count,
+        faulty_count: cell.faulty_count,
+        node_factory: factory,
+        node_factories: &[],
+        byz_node_factory: None,
+        byz_placement: ByzPlacement::None,
+        seed,
+        // the run is judged by evaluate_consensus below
+        check_termination: false,
+        overrides: None,
+        faults: None,
+        network: NetworkConfig {
+            drop_rate: cell.drop_rate,
+            dupl_rate: 0.0,
+            delays: Some((cell.min_delay, cell.max_delay)),
+        },
+    };
+    let mut sys = build_system(&config);
+    let nodes = sys.get_node_ids();
+
+    let init_values = cell.input.generate(cell.node_count, seed);
+    send_init_messages(&mut sys, &init_values);
+    // protocols like SafeBBC never stop on their own, so the run ends
+    // as soon as every node decided
+    while sys.time() < cell.max_time && sys.step_for_duration(1.0) {
+        if nodes.iter().all(|node| !get_local_messages(&sys, node).is_empty()) {
+            break;
+        }
+    }
+
+    let violations = evaluate_consensus(&sys, &nodes, &init_values, cell.input.validity());
+
+    let mut decided_value = None;
+    let mut rounds = Vec::new();
+    for node in nodes.iter() {
+        // malformed results are reported by evaluate_consensus
+        if let Some(result) = get_local_messages(&sys, node).iter().find_map(|m| MessageResult::decode(m).ok()) {
+            decided_value = decided_value.or(Some(result.value));
+            rounds.extend(result.round);
+        }
+    }
+
+    let latencies: Vec<f64> = get_decision_latencies(&sys, &nodes).iter().map(|(_, l)| *l).collect();
+    let latency = LatencyStats::from_samples(&latencies);
+
+    SweepRow {
+        protocol: cell.protocol.clone(),
+        node_count: cell.node_count,
+        faulty_count: cell.faulty_count,
+        input: cell.input.to_string(),
+        min_delay: cell.min_delay,
+        max_delay: cell.max_delay,
+        drop_rate: cell.drop_rate,
+        seed,
+        passed: violations.is_empty(),
+        error: violations
+            .iter()
+            .map(|(property, details)| format!("{:?} violated: {}", property, details))
+            .collect::<Vec<_>>()
+            .join("; "),
+        decided_value,
+        avg_round: if rounds.is_empty() {
+            None
+        } else {
+            Some(rounds.iter().sum::<u64>() as f64 / rounds.len() as f64)
+        },
+        max_round: rounds.iter().max().copied(),
+        latency_min: latency.map(|l| l.min),
+        latency_median: latency.map(|l| l.median),
+        latency_max: latency.map(|l| l.max),
+        latency_p99: latency.map(|l| l.p99),
+        messages: get_message_counter(&sys).total(),
+        sim_time: sys.time(),
+    }
+}
+
+/// Entry point of a worker process: runs every seed of the cell given as JSON
+/// and prints one row per run to stdout.
+pub fn run_worker(cell_json: &str, impl_path: &str) {
+    let cell: SweepCell = serde_json::from_str(cell_json).unwrap();
+    let factory = PyNodeFactory::new(impl_path, &cell.protocol);
+    for seed in cell.seeds.0..=cell.seeds.1 {
+        let row = run_seed(&cell, &factory, seed);
+        println!("{}{}", ROW_PREFIX, serde_json::to_string(&row).unwrap());
+    }
+}
+
+fn run_cell_process(cell: &SweepCell, worker_args: &[String]) -> Result<Vec<SweepRow>, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let cell_json = serde_json::to_string(cell).unwrap();
+    let child = Command::new(exe)
+        .args(worker_args)
+        .arg("--sweep-worker")
+        .arg(&cell_json)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Can't start sweep worker: {}", e))?;
+
+    let mut rows = Vec::new();
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    for line in BufReader::new(&output.stdout[..]).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if let Some(row) = line.strip_prefix(ROW_PREFIX) {
+            rows.push(serde_json::from_str(row).map_err(|e| e.to_string())?);
+        }
+    }
+    if !output.status.success() {
+        return Err(format!("Sweep worker for {} failed with {}", cell_json, output.status));
+    }
+    Ok(rows)
+}
+
+/// Number of parallel workers, the number of CPUs if not given.
+pub fn jobs_or_default(jobs: Option<usize>) -> usize {
+    jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// Command line entry point: runs the sweep described by the JSON grid file
+/// and exits with code 1 if a worker failed or some run violated consensus.
+pub fn run_and_exit(grid_path: &str, impl_path: &str, jobs: Option<usize>, output: Option<&str>) -> ! {
+    let worker_args = vec!["--impl".to_string(), impl_path.to_string()];
+    let result = SweepGrid::load(grid_path)
+        .and_then(|grid| run_sweep(&grid, &worker_args, jobs_or_default(jobs), output));
+    match result {
+        Ok(0) => std::process::exit(0),
+        Ok(failed_runs) => {
+            eprintln!("Sweep finished, {} runs failed", failed_runs);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Sweep failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs every cell of `grid` in up to `jobs` parallel worker processes,
+/// which are started as `<current exe> <worker_args> --sweep-worker <cell>`,
+/// and passes the index of every finished cell in `grid.cells()` with its rows
+/// to `on_rows`. Cells finish in any order.
+pub fn run_cells<F>(grid: &SweepGrid, worker_args: &[String], jobs: usize, mut on_rows: F) -> Result<(), String>
+where
+    F: FnMut(usize, Vec<SweepRow>) -> Result<(), String>
+{
+    let cells = Mutex::new(grid.cells().into_iter().enumerate().collect::<Vec<_>>());
+    let (sender, receiver) = mpsc::channel();
+    let mut error = None;
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            let sender = sender.clone();
+            let cells = &cells;
+            scope.spawn(move || loop {
+                let (index, cell) = match cells.lock().unwrap().pop() {
+                    Some(cell) => cell,
+                    None => break,
+                };
+                if sender.send((index, run_cell_process(&cell, worker_args))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, result) in receiver {
+            if let Err(e) = result.and_then(|rows| on_rows(index, rows)) {
+                error = Some(e);
+            }
+        }
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Runs the sweep and writes one row per run to `output` (stdout if `None`),
+/// ordered by cell and seed. Returns the number of failed runs.
+pub fn run_sweep(
+    grid: &SweepGrid,
+    worker_args: &[String],
+    jobs: usize,
+    output: Option<&str>
+) -> Result<usize, String> {
+    let format = OutputFormat::from_path(output);
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path).map_err(|e| format!("Can't create {}: {}", path, e))?),
+        None => Box::new(io::stdout()),
+    };
+    if format == OutputFormat::Csv {
+        writeln!(out, "{}", CSV_HEADER).map_err(|e| e.to_string())?;
+    }
+
+    let mut rows = Vec::new();
+    run_cells(grid, worker_args, jobs, |index, cell_rows| {
+        rows.extend(cell_rows.into_iter().map(|row| (index, row)));
+        Ok(())
+    })?;
+    rows.sort_by_key(|(index, row)| (*index, row.seed));
+
+    let mut failed_runs = 0;
+    for (_, row) in rows {
+        if !row.passed {
+            failed_runs += 1;
+        }
+        let line = match format {
+            OutputFormat::Csv => row.to_csv(),
+            OutputFormat::JsonLines => serde_json::to_string(&row).unwrap(),
+        };
+        writeln!(out, "{}", line).map_err(|e| e.to_string())?;
+    }
+    Ok(failed_runs)
+}
@@ -0,0 +1,367 @@
+use std::cell::RefCell;
+use std::collections::{ BTreeMap, VecDeque };
+use std::fs::{ self, File };
+use std::io::{ LineWriter, Write };
+use std::rc::Rc;
+
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use serde_json::{ json, Value };
+use sugars::{ rc, refcell };
+
+use dslib::node::{ Context, Node };
+use dslib::pynode::JsonMessage;
+
+use super::events::{ self, Event };
+use super::TestConfig;
+
+/// Expected deliveries of a replayed run: (sender, type, data) by receiver.
+type Deliveries = BTreeMap<String, VecDeque<(String, String, Value)>>;
+
+enum Sink {
+    File(LineWriter<File>),
+    /// Trace of a replayed test, compared with the recorded one afterwards
+    Memory(Vec<Value>),
+}
+
+struct Tracer {
+    sink: Sink,
+    /// Runs (built systems) of the current test
+    runs: usize,
+    /// Deliveries of every recorded run of the replayed test
+    recorded_runs: Vec<Deliveries>,
+    /// Deliveries of the current run which are still to come
+    expected: Deliveries,
+}
+
+impl Tracer {
+    fn write(&mut self, line: Value) {
+        match &mut self.sink {
+            Sink::File(out) => {
+                if let Err(e) = writeln!(out, "{}", line) {
+                    eprintln!("Can't write the trace: {}", e);
+                    std::process::exit(2);
+                }
+            }
+            Sink::Memory(lines) => lines.push(line),
+        }
+    }
+}
+
+thread_local! {
+    static TRACER: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+}
+
+fn with_tracer(f: impl FnOnce(&mut Tracer)) {
+    TRACER.with(|tracer| {
+        if let Some(tracer) = tracer.borrow_mut().as_mut() {
+            f(tracer);
+        }
+    });
+}
+
+pub fn is_enabled() -> bool {
+    TRACER.with(|tracer| tracer.borrow().is_some())
+}
+
+/// Makes the Python nodes report their timer actions (see `Context.timer_log`
+/// in dslib.py), which dslib doesn't log.
+fn enable_timer_log() {
+    let enabled = Python::with_gil(|py| -> PyResult<()> {
+        py.import("dslib")?.getattr("Context")?.setattr("timer_log", PyList::empty(py))
+    });
+    if let Err(e) = enabled {
+        eprintln!("Timers of Python nodes won't be traced: {}", e);
+    }
+}
+
+fn take_timer_actions() -> Vec<(String, f64)> {
+    Python::with_gil(|py| -> PyResult<Vec<(String, f64)>> {
+        let log = py.import("dslib")?.getattr("Context")?.getattr("timer_log")?;
+        if log.is_none() {
+            return Ok(Vec::new());
+        }
+        let actions = log.extract()?;
+        log.call_method0("clear")?;
+        Ok(actions)
+    })
+    .unwrap_or_default()
+}
+
+/// Starts writing the trace of every following run to `path`, a JSON object
+/// per line. The first line holds the command line arguments, so that the
+/// trace can be replayed with `--replay`. Exits if the file can't be created.
+pub fn start(path: &str) {
+    let file = File::create(path).unwrap_or_else(|e| {
+        eprintln!("Can't create the trace {}: {}", path, e);
+        std::process::exit(2);
+    });
+    enable_timer_log();
+    let mut tracer = Tracer {
+        sink: Sink::File(LineWriter::new(file)),
+        runs: 0,
+        recorded_runs: Vec::new(),
+        expected: Deliveries::new(),
+    };
+    tracer.write(json!({ "event": "trace", "args": std::env::args().collect::<Vec<_>>() }));
+    TRACER.with(|t| *t.borrow_mut() = Some(tracer));
+}
+
+pub fn begin_test(name: &str, seed: u64) {
+    with_tracer(|tracer| {
+        tracer.runs = 0;
+        tracer.write(json!({ "event": "test", "test": name, "seed": seed }));
+    });
+}
+
+pub fn end_test(result: &dslib::test::TestResult) {
+    with_tracer(|tracer| match result {
+        Ok(_) => tracer.write(json!({ "event": "result", "passed": true })),
+        Err(e) => tracer.write(json!({ "event": "result", "passed": false, "error": e })),
+    });
+}
+
+/// Called for every built system, before its nodes are built.
+pub(super) fn begin_run(config: &TestConfig) {
+    with_tracer(|tracer| {
+        tracer.expected = tracer.recorded_runs.get(tracer.runs).cloned().unwrap_or_default();
+        tracer.write(json!({
+            "event": "run",
+            "run": tracer.runs,
+            "seed": config.seed,
+            "nodes": config.node_count,
+            "faulty": config.faulty_count,
+        }));
+        tracer.runs += 1;
+    });
+}
+
+pub(super) fn on_event(event: &Event) {
+    with_tracer(|tracer| tracer.write(event_json(event)));
+}
+
+fn message_data(msg: &JsonMessage) -> Value {
+    serde_json::from_str(&msg.data).unwrap_or_else(|_| Value::String(msg.data.clone()))
+}
+
+/// Trace line of an event.
+pub fn event_json(event: &Event) -> Value {
+    match event {
+        Event::MessageSend { time, src, dest, msg } => json!({
+            "event": "send", "time": time, "src": src, "dest": dest, "type": msg.tip, "data": message_data(msg)
+        }),
+        Event::MessageReceive { time, src, dest, msg } => json!({
+            "event": "deliver", "time": time, "src": src, "dest": dest, "type": msg.tip, "data": message_data(msg)
+        }),
+        Event::MessageDrop { time, src, dest, msg } => json!({
+            "event": "drop", "time": time, "src": src, "dest": dest, "type": msg.tip, "data": message_data(msg)
+        }),
+        Event::LocalMessageSend { time, node, msg } => json!({
+            "event": "local_send", "time": time, "node": node, "type": msg.tip, "data": message_data(msg)
+        }),
+        Event::LocalMessageReceive { time, node, msg } => json!({
+            "event": "local_deliver", "time": time, "node": node, "type": msg.tip, "data": message_data(msg)
+        }),
+        Event::TimerSet { time, node, timer, delay } => json!({
+            "event": "timer_set", "time": time, "node": node, "timer": timer, "delay": delay
+        }),
+        Event::TimerCancel { time, node, timer } => json!({
+            "event": "timer_cancel", "time": time, "node": node, "timer": timer
+        }),
+        Event::TimerFired { time, node, timer } => json!({
+            "event": "timer_fire", "time": time, "node": node, "timer": timer
+        }),
+        Event::NodeCrashed { time, node } => json!({ "event": "crash", "time": time, "node": node }),
+        Event::NodeDisconnected { time, node } => json!({ "event": "disconnect", "time": time, "node": node }),
+        Event::NodeConnected { time, node } => json!({ "event": "connect", "time": time, "node": node }),
+        Event::Network { time, description } => json!({ "event": "network", "time": time, "description": description }),
+    }
+}
+
+/// Test of a recorded trace: the "test" line and everything up to the next one.
+pub struct RecordedTest {
+    pub name: String,
+    pub seed: u64,
+    pub lines: Vec<Value>,
+}
+
+pub struct Recording {
+    /// Command line arguments of the recorded run
+    pub args: Vec<String>,
+    pub tests: Vec<RecordedTest>,
+}
+
+/// Reads a trace written with `start`, exits if it is malformed.
+pub fn load(path: &str) -> Recording {
+    let fail = |e: String| -> ! {
+        eprintln!("Bad trace {}: {}", path, e);
+        std::process::exit(2);
+    };
+    let text = fs::read_to_string(path).unwrap_or_else(|e| fail(e.to_string()));
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    let header: Value = match lines.next() {
+        Some((_, line)) => serde_json::from_str(line).unwrap_or_else(|e| fail(format!("line 1: {}", e))),
+        None => fail("empty trace".to_string()),
+    };
+    let args = match header["args"].as_array() {
+        Some(args) if header["event"] == "trace" => args.iter().map(|arg| arg.as_str().unwrap_or_default().to_string()).collect(),
+        _ => fail("line 1: no command line arguments".to_string()),
+    };
+
+    let mut tests: Vec<RecordedTest> = Vec::new();
+    for (idx, line) in lines {
+        let value: Value = serde_json::from_str(line).unwrap_or_else(|e| fail(format!("line {}: {}", idx + 1, e)));
+        if value["event"] == "test" {
+            let name = value["test"].as_str().unwrap_or_else(|| fail(format!("line {}: no test name", idx + 1)));
+            let seed = value["seed"].as_u64().unwrap_or_else(|| fail(format!("line {}: no seed", idx + 1)));
+            tests.push(RecordedTest { name: name.to_string(), seed, lines: Vec::new() });
+        }
+        match tests.last_mut() {
+            Some(test) => test.lines.push(value),
+            None => fail(format!("line {}: event outside of a test", idx + 1)),
+        }
+    }
+    Recording { args, tests }
+}
+
+/// Deliveries of every run of a recorded test, in the recorded order.
+fn recorded_deliveries(lines: &[Value]) -> Vec<Deliveries> {
+    let mut runs: Vec<Deliveries> = Vec::new();
+    for line in lines {
+        if line["event"] == "run" {
+            runs.push(Deliveries::new());
+        } else if line["event"] == "deliver" {
+            if let Some(run) = runs.last_mut() {
+                let dest = line["dest"].as_str().unwrap_or_default().to_string();
+                let src = line["src"].as_str().unwrap_or_default().to_string();
+                let tip = line["type"].as_str().unwrap_or_default().to_string();
+                run.entry(dest).or_default().push_back((src, tip, line["data"].clone()));
+            }
+        }
+    }
+    runs
+}
+
+/// Starts recording the trace of a rerun of `test` in memory, its nodes get
+/// the messages in the recorded order.
+pub fn begin_replay(test: &RecordedTest) {
+    enable_timer_log();
+    let tracer = Tracer {
+        sink: Sink::Memory(Vec::new()),
+        runs: 0,
+        recorded_runs: recorded_deliveries(&test.lines),
+        expected: Deliveries::new(),
+    };
+    TRACER.with(|t| *t.borrow_mut() = Some(tracer));
+}
+
+/// Stops the replay and returns its trace.
+pub fn end_replay() -> Vec<Value> {
+    match TRACER.with(|t| t.borrow_mut().take()) {
+        Some(Tracer { sink: Sink::Memory(lines), .. }) => lines,
+        _ => Vec::new(),
+    }
+}
+
+/// Compares a replayed trace with the recorded one, returns the number of
+/// events or the first difference.
+pub fn compare(recorded: &[Value], replayed: &[Value]) -> Result<usize, String> {
+    for (idx, (expected, actual)) in recorded.iter().zip(replayed.iter()).enumerate() {
+        if expected != actual {
+            return Err(format!("event {}: recorded {}, replayed {}", idx + 1, expected, actual));
+        }
+    }
+    if recorded.len() != replayed.len() {
+        let (longer, what) = if recorded.len() > replayed.len() { (recorded, "missing") } else { (replayed, "extra") };
+        let shorter = recorded.len().min(replayed.len());
+        return Err(format!("event {}: {} {}", shorter + 1, what, longer[shorter]));
+    }
+    Ok(recorded.len())
+}
+
+/// Whether `msg` from `from` is the next delivery of `node` in the replayed
+/// run. Always true when not replaying or past the end of the recorded run.
+fn is_next_delivery(node: &str, msg: &JsonMessage, from: &str) -> bool {
+    TRACER.with(|tracer| {
+        let tracer = tracer.borrow();
+        let next = tracer.as_ref().and_then(|tracer| tracer.expected.get(node)?.front());
+        match next {
+            Some((src, tip, data)) => src == from && *tip == msg.tip && *data == message_data(msg),
+            None => true,
+        }
+    })
+}
+
+fn pop_delivery(node: &str) {
+    with_tracer(|tracer| {
+        if let Some(expected) = tracer.expected.get_mut(node) {
+            expected.pop_front();
+        }
+    });
+}
+
+/// Wraps a node of a traced system: records the timer actions of the inner
+/// node and, in a replay, holds back the messages which arrive ahead of
+/// their recorded order.
+pub struct TracedNode {
+    id: String,
+    inner: Rc<RefCell<dyn Node<JsonMessage>>>,
+    held: Vec<(JsonMessage, String)>,
+}
+
+impl TracedNode {
+    fn record_timer_actions(&self, ctx: &mut Context<JsonMessage>) {
+        let time = ctx.time();
+        for (timer, delay) in take_timer_actions() {
+            let node = self.id.clone();
+            if delay < 0.0 {
+                events::record(Event::TimerCancel { time, node, timer });
+            } else {
+                events::record(Event::TimerSet { time, node, timer, delay });
+            }
+        }
+    }
+
+    fn deliver(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        pop_delivery(&self.id);
+        self.inner.borrow_mut().on_message(msg, from, ctx);
+        self.record_timer_actions(ctx);
+    }
+}
+
+impl Node<JsonMessage> for TracedNode {
+    fn id(&self) -> &String {
+        &self.id
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        if !is_next_delivery(&self.id, &msg, &from) {
+            self.held.push((msg, from));
+            return;
+        }
+        self.deliver(msg, from, ctx);
+        while let Some(idx) = self.held.iter().position(|(msg, from)| is_next_delivery(&self.id, msg, from)) {
+            let (msg, from) = self.held.remove(idx);
+            self.deliver(msg, from, ctx);
+        }
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        self.inner.borrow_mut().on_local_message(msg, ctx);
+        self.record_timer_actions(ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        self.inner.borrow_mut().on_timer(timer, ctx);
+        self.record_timer_actions(ctx);
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.inner.borrow_mut().max_size()
+    }
+}
+
+pub(super) fn wrap(node_id: &str, inner: Rc<RefCell<dyn Node<JsonMessage>>>) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+    rc!(refcell!(TracedNode { id: node_id.to_string(), inner, held: Vec::new() }))
+}
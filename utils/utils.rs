@@ -1,36 +1,242 @@
 use assertables::assume;
 use env_logger::Builder;
 use log::LevelFilter;
-use serde::Serialize;
-use serde_json::Value;
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+use serde::{ Deserialize, Serialize };
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::io::Write;
+use std::rc::Rc;
 use sugars::{ rc, refcell };
 
 use dslib::pynode::{ JsonMessage, PyNodeFactory };
 use dslib::test::{ TestResult };
-use dslib::node::LocalEventType;
+use dslib::node::{ LocalEventType, Node };
 use dslib::system::System;
 
-#[derive(Serialize)]
-pub struct MessageInit {
-    pub value: u64,
+#[allow(dead_code)]
+pub mod events;
+#[allow(dead_code)]
+pub mod sweep;
+#[allow(dead_code)]
+pub mod regression;
+#[allow(dead_code)]
+pub mod fuzz;
+#[allow(dead_code)]
+pub mod shrink;
+#[allow(dead_code)]
+pub mod faults;
+#[allow(dead_code)]
+pub mod partition;
+#[allow(dead_code)]
+pub mod gst;
+#[allow(dead_code)]
+pub mod adversary;
+#[allow(dead_code)]
+pub mod scheduler;
+#[allow(dead_code)]
+pub mod explore;
+#[allow(dead_code)]
+pub mod trace;
+#[allow(dead_code)]
+pub mod monitor;
+#[allow(dead_code)]
+pub mod messages;
+#[allow(dead_code)]
+pub mod report;
+#[allow(dead_code)]
+pub mod matrix;
+
+use messages::{ MessageFields, MessageInit, MessageResult, ProtocolMessage };
+
+/// Which nodes are built with `byz_node_factory` by `build_system_with_byz`.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+pub enum ByzPlacement<'a> {
+    /// No Byzantine nodes
+    None,
+    /// Exactly the listed node ids
+    Explicit(&'a [u32]),
+    /// Nodes `0..k`
+    First(u32),
+    /// Nodes `n-k..n`
+    Last(u32),
+    /// `k` distinct nodes picked with the test seed
+    Random(u32),
+    /// Coordinators `(r - 1) % n` of rounds `1..=k`
+    Coordinators(u32),
+}
+
+/// Builds protocol nodes, so that Python and native Rust implementations
+/// can be mixed in a single `System<JsonMessage>`.
+pub trait NodeFactory {
+    fn build_node(
+        &self,
+        node_id: &str,
+        node_ids: &[String],
+        faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>>;
+}
+
+impl NodeFactory for PyNodeFactory {
+    fn build_node(
+        &self,
+        node_id: &str,
+        node_ids: &[String],
+        faulty_count: u32,
+        seed: u64
+    ) -> Rc<RefCell<dyn Node<JsonMessage>>> {
+        let node = self.build(node_id, (node_id, node_ids.to_vec(), faulty_count, seed), seed);
+        rc!(refcell!(node))
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct TestConfig<'a> {
     pub node_count: u32,
     pub faulty_count: u32,
-    pub node_factory: &'a PyNodeFactory,
-    pub byz_node_factory: Option<&'a PyNodeFactory>,
+    pub node_factory: &'a dyn NodeFactory,
+    /// Per-node overrides of `node_factory` as (node id, factory) pairs
+    pub node_factories: &'a [(u32, &'a dyn NodeFactory)],
+    pub byz_node_factory: Option<&'a dyn NodeFactory>,
+    pub byz_placement: ByzPlacement<'a>,
     pub seed: u64,
     pub check_termination: bool,
+    /// Changes of the test scenario made by the failure shrinker
+    pub overrides: Option<&'a RunOverrides>,
+    /// Faults injected by `run_fault_timeline`
+    pub faults: Option<&'a faults::FaultTimeline>,
+    /// Network conditions set up by `build_system`
+    pub network: NetworkConfig,
+}
+
+/// Command line options shared by every test of a suite, from which the
+/// suite builds the `TestConfig` of each test.
+#[derive(Copy, Clone)]
+pub struct SuiteOptions<'a> {
+    pub node_count: u32,
+    pub faulty_count: u32,
+    pub seed: u64,
+    pub overrides: Option<&'a RunOverrides>,
+    pub faults: Option<&'a faults::FaultTimeline>,
+    pub network: NetworkConfig,
+}
+
+impl<'a> SuiteOptions<'a> {
+    /// Config of correct nodes built by `node_factory` without termination checks.
+    pub fn config(&self, node_factory: &'a dyn NodeFactory) -> TestConfig<'a> {
+        TestConfig {
+            node_count: self.node_count,
+            faulty_count: self.faulty_count,
+            node_factory,
+            node_factories: &[],
+            byz_node_factory: None,
+            byz_placement: ByzPlacement::None,
+            seed: self.seed,
+            check_termination: false,
+            overrides: self.overrides,
+            faults: self.faults,
+            network: self.network,
+        }
+    }
+}
+
+/// Message loss, duplication and delays applied to every built system.
+/// Tests which set their own delays (e.g. latency tests) override `delays`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NetworkConfig {
+    pub drop_rate: f64,
+    pub dupl_rate: f64,
+    /// (min, max) message delay, dslib default if not set
+    pub delays: Option<(f64, f64)>,
+}
+
+impl NetworkConfig {
+    /// Parses the delay range given with `--delays`, "min..max" or a single
+    /// delay. Exits if it is malformed.
+    #[allow(dead_code)]
+    pub fn parse_delays(text: &str) -> (f64, f64) {
+        let parsed = match text.split_once("..") {
+            Some((min, max)) => min.trim().parse::<f64>().and_then(|min| max.trim().parse::<f64>().map(|max| (min, max))),
+            None => text.trim().parse::<f64>().map(|delay| (delay, delay)),
+        };
+        match parsed {
+            Ok((min, max)) if min >= 0.0 && min <= max => (min, max),
+            _ => {
+                eprintln!("Bad delay range {}, expected min..max", text);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    fn apply(&self, sys: &mut System<JsonMessage>) {
+        sys.set_drop_rate(self.drop_rate);
+        sys.set_dupl_rate(self.dupl_rate);
+        if let Some((min_delay, max_delay)) = self.delays {
+            sys.set_delays(min_delay, max_delay);
+        }
+    }
+}
+
+/// Changes of a test scenario, applied by the helpers which tests use to
+/// pick init values (`get_init_values`) and inject faults (`crash_node`,
+/// `disconnect_node`, `step_before_fault`).
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunOverrides {
+    /// Replaces the init values generated by the test, if the length matches
+    #[serde(default)]
+    pub init_values: Option<Vec<u64>>,
+    /// Faults which are not injected, by their order in the run
+    #[serde(default)]
+    pub skipped_faults: Vec<usize>,
+    /// Faults which are injected without stepping the system before them
+    #[serde(default)]
+    pub early_faults: Vec<usize>,
+}
+
+impl RunOverrides {
+    /// Parses the JSON given with `--overrides`, exits if it is malformed.
+    #[allow(dead_code)]
+    pub fn parse(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_else(|e| {
+            eprintln!("Bad overrides {}: {}", json, e);
+            std::process::exit(2);
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        *self == RunOverrides::default()
+    }
+}
+
+/// Init values and number of faults of the current run, recorded by the
+/// scenario helpers for the failure shrinker.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default)]
+pub struct RunRecord {
+    pub init_values: Vec<u64>,
+    pub fault_count: usize,
+}
+
+thread_local! {
+    static RUN_RECORD: RefCell<RunRecord> = RefCell::new(RunRecord::default());
+}
+
+#[allow(dead_code)]
+pub fn get_run_record() -> RunRecord {
+    RUN_RECORD.with(|record| record.borrow().clone())
 }
 
 pub fn init_logger(level: LevelFilter) {
-    Builder::new()
+    let logger = Builder::new()
         .filter(None, level)
         .format(|buf, record| writeln!(buf, "{}", record.args()))
-        .init();
+        .build();
+    events::CapturingLogger::init(logger);
 }
 
 pub fn get_local_messages(sys: &System<JsonMessage>, node: &str) -> Vec<JsonMessage> {
@@ -41,64 +247,229 @@ pub fn get_local_messages(sys: &System<JsonMessage>, node: &str) -> Vec<JsonMess
         .collect::<Vec<_>>()
 }
 
+/// Number of the last events of a run printed by `print_diagnostics`.
+const DIAGNOSTIC_EVENTS: usize = 20;
+
+/// Prints the state of a run a check failed on: the simulated time, the local
+/// messages of every node and the last events other than local messages.
+/// Nothing is printed while the trace is quiet (exploring, shrinking).
 #[allow(dead_code)]
-pub fn build_system(config: &TestConfig) -> System<JsonMessage> {
-    let mut sys = System::with_seed(config.seed);
-    let mut node_ids = Vec::new();
-    for n in 0..config.node_count {
-        node_ids.push(format!("{}", n));
+pub fn print_diagnostics(sys: &System<JsonMessage>) {
+    if events::is_quiet() {
+        return;
     }
-    for node_id in node_ids.iter() {
-        let node = config.node_factory.build(
-            node_id,
-            (node_id, node_ids.clone(), config.faulty_count, config.seed),
-            config.seed
-        );
-        sys.add_node(rc!(refcell!(node)));
+    println!("diagnostics at time {:.3}:", sys.time());
+    for node in sys.get_node_ids() {
+        let messages = get_local_messages(sys, &node);
+        if messages.is_empty() {
+            println!("  node {}: no local messages", node);
+        } else {
+            let messages: Vec<String> = messages.iter().map(|msg| format!("{:?}", msg)).collect();
+            println!("  node {}: {}", node, messages.join(", "));
+        }
+    }
+    let run_events: Vec<events::Event> = events::get_events()
+        .into_iter()
+        .filter(|event| !matches!(event, events::Event::LocalMessageSend { .. } | events::Event::LocalMessageReceive { .. }))
+        .collect();
+    let last = &run_events[run_events.len().saturating_sub(DIAGNOSTIC_EVENTS)..];
+    println!("  last {} of {} events:", last.len(), run_events.len());
+    for event in last {
+        println!("  {}", event);
     }
-    return sys;
 }
 
+/// Prints the diagnostics of `sys` if `result` is a failure.
 #[allow(dead_code)]
-pub fn build_system_with_byz(config: &TestConfig) -> System<JsonMessage> {
-    let mut sys = System::with_seed(config.seed);
+pub fn with_diagnostics(sys: &System<JsonMessage>, result: TestResult) -> TestResult {
+    if result.is_err() {
+        print_diagnostics(sys);
+    }
+    result
+}
+
+/// Factory of the node with index `idx`, ignoring Byzantine placement.
+fn get_node_factory<'a>(config: &TestConfig<'a>, idx: u32) -> &'a dyn NodeFactory {
+    config.node_factories
+        .iter()
+        .rev()
+        .find(|(id, _)| *id == idx)
+        .map_or(config.node_factory, |(_, factory)| *factory)
+}
+
+fn build_nodes(
+    sys: &mut System<JsonMessage>,
+    config: &TestConfig,
+    byz_nodes: &[u32],
+    seed: u64
+) {
+    trace::begin_run(config);
+    report::begin_run();
+    events::clear();
+    RUN_RECORD.with(|record| *record.borrow_mut() = RunRecord::default());
     let mut node_ids = Vec::new();
     for n in 0..config.node_count {
         node_ids.push(format!("{}", n));
     }
-    for node_id in node_ids.iter() {
-        let node;
-        if node_id == "0" {
-            node = config.byz_node_factory.unwrap().build(
-                    node_id,
-                    (node_id, node_ids.clone(), config.faulty_count, config.seed),
-                    config.seed
-                );
+    for (idx, node_id) in node_ids.iter().enumerate() {
+        let idx = idx as u32;
+        let factory = if byz_nodes.contains(&idx) {
+            config.byz_node_factory.unwrap()
+        } else {
+            get_node_factory(config, idx)
+        };
+        let node = factory.build_node(node_id, &node_ids, config.faulty_count, seed);
+        if trace::is_enabled() {
+            sys.add_node(trace::wrap(node_id, node));
         } else {
-            node = config.node_factory.build(
-                node_id,
-                (node_id, node_ids.clone(), config.faulty_count, config.seed),
-                config.seed
-            );
+            sys.add_node(node);
         }
-
-        sys.add_node(rc!(refcell!(node)));
     }
-    return sys;
+    config.network.apply(sys);
+}
+
+#[allow(dead_code)]
+pub fn build_system(config: &TestConfig) -> System<JsonMessage> {
+    let mut sys = System::with_seed(config.seed);
+    build_nodes(&mut sys, config, &[], config.seed);
+    sys
+}
+
+/// Coordinator of `round`, rounds start from 1.
+#[allow(dead_code)]
+pub fn coordinator(round: u32, node_count: u32) -> u32 {
+    assert!(round >= 1, "rounds start from 1, there is no coordinator of round {}", round);
+    (round - 1) % node_count
+}
+
+/// Returns sorted ids of the nodes selected by `config.byz_placement`.
+/// Panics if the placement doesn't fit into `node_count` / `faulty_count`.
+#[allow(dead_code)]
+pub fn get_byzantine_nodes(config: &TestConfig) -> Vec<u32> {
+    let n = config.node_count;
+    let mut byz_nodes: Vec<u32> = match config.byz_placement {
+        ByzPlacement::None => Vec::new(),
+        ByzPlacement::Explicit(ids) => ids.to_vec(),
+        ByzPlacement::First(k) => (0..k.min(n)).collect(),
+        ByzPlacement::Last(k) => (n - k.min(n)..n).collect(),
+        ByzPlacement::Random(k) => {
+            let mut rand = Pcg64::seed_from_u64(config.seed);
+            rand::seq::index::sample(&mut rand, n as usize, k.min(n) as usize)
+                .into_iter()
+                .map(|idx| idx as u32)
+                .collect()
+        }
+        ByzPlacement::Coordinators(k) => (1..=k).map(|r| coordinator(r, n)).collect(),
+    };
+    byz_nodes.sort_unstable();
+    byz_nodes.dedup();
+
+    assert!(
+        byz_nodes.iter().all(|&id| id < n),
+        "Byzantine placement {:?} refers to nodes outside 0..{}", config.byz_placement, n
+    );
+    assert!(
+        byz_nodes.len() as u32 <= config.faulty_count,
+        "Byzantine placement {:?} selects {} nodes, but faulty_count is {}",
+        config.byz_placement, byz_nodes.len(), config.faulty_count
+    );
+    byz_nodes
+}
+
+/// Ids of the nodes which are not selected by `config.byz_placement`.
+#[allow(dead_code)]
+pub fn get_correct_nodes(config: &TestConfig) -> Vec<String> {
+    let byz_nodes = get_byzantine_nodes(config);
+    (0..config.node_count)
+        .filter(|id| !byz_nodes.contains(id))
+        .map(|id| id.to_string())
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn build_system_with_byz(config: &TestConfig) -> System<JsonMessage> {
+    let byz_nodes = get_byzantine_nodes(config);
+    let mut sys = System::with_seed(config.seed);
+    build_nodes(&mut sys, config, &byz_nodes, config.seed);
+    sys
 }
 
 #[allow(dead_code)]
 pub fn send_init_messages(sys: &mut System<JsonMessage>, init_values: &Vec<u64>) {
     for (idx, init_value) in init_values.iter().enumerate() {
         sys.send_local(
-            JsonMessage::from("INIT", &(MessageInit { value: *init_value })),
+            MessageInit { value: *init_value }.encode(),
             &format!("{}", idx)
         );
     }
 }
 
+/// Init values of a test: `generated`, unless the overrides replace them.
+#[allow(dead_code)]
+pub fn get_init_values(config: &TestConfig, generated: Vec<u64>) -> Vec<u64> {
+    let init_values = match config.overrides.and_then(|o| o.init_values.as_ref()) {
+        Some(values) if values.len() == generated.len() => values.clone(),
+        _ => generated,
+    };
+    RUN_RECORD.with(|record| record.borrow_mut().init_values = init_values.clone());
+    init_values
+}
+
+fn is_overridden(config: &TestConfig, select: fn(&RunOverrides) -> &Vec<usize>, fault: usize) -> bool {
+    config.overrides.is_some_and(|o| select(o).contains(&fault))
+}
+
+/// Steps the system for `duration` before the next fault, unless the
+/// overrides inject this fault early.
+#[allow(dead_code)]
+pub fn step_before_fault(sys: &mut System<JsonMessage>, config: &TestConfig, duration: f64) {
+    let fault = RUN_RECORD.with(|record| record.borrow().fault_count);
+    if !is_overridden(config, |o| &o.early_faults, fault) {
+        sys.step_for_duration(duration);
+    }
+}
+
+/// Registers the next fault, returns false if the overrides skip it.
+fn next_fault(config: &TestConfig) -> bool {
+    let fault = RUN_RECORD.with(|record| {
+        let mut record = record.borrow_mut();
+        record.fault_count += 1;
+        record.fault_count - 1
+    });
+    !is_overridden(config, |o| &o.skipped_faults, fault)
+}
+
+#[allow(dead_code)]
+pub fn crash_node(sys: &mut System<JsonMessage>, config: &TestConfig, node: &str) {
+    if next_fault(config) {
+        sys.crash_node(node);
+    }
+}
+
+#[allow(dead_code)]
+pub fn disconnect_node(sys: &mut System<JsonMessage>, config: &TestConfig, node: &str) {
+    if next_fault(config) {
+        sys.disconnect_node(node);
+    }
+}
+
+/// Runs `config.faults`, returns the nodes which are faulty afterwards.
+#[allow(dead_code)]
+pub fn run_fault_timeline(sys: &mut System<JsonMessage>, config: &TestConfig) -> Result<Vec<String>, String> {
+    match config.faults {
+        Some(timeline) => timeline.run(sys),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[allow(dead_code)]
+pub fn check_consensus(sys: &mut System<JsonMessage>, nodes: &Vec<String>, expected_result: Option<u64>) -> TestResult {
+    let result = check_consensus_results(sys, nodes, expected_result);
+    with_diagnostics(sys, result)
+}
+
 #[allow(dead_code)]
-pub fn check_consensus(
+fn check_consensus_results(
     sys: &mut System<JsonMessage>,
     nodes: &Vec<String>,
     mut expected_result: Option<u64>
@@ -115,8 +486,7 @@ pub fn check_consensus(
         assume!(messages.len() == 1, format!("Node {}: Wrong number of messages!", node))?;
         assume!(messages[0].tip == "RESULT", format!("Node {}: Wrong message type!", node))?;
 
-        let data: Value = serde_json::from_str(&messages[0].data).unwrap();
-        let value = data["value"].as_u64().unwrap();
+        let value = MessageResult::decode(&messages[0]).map_err(|e| format!("Node {}: {}", node, e))?.value;
         if expected_result.is_none() {
             expected_result = Some(value);
         }
@@ -128,8 +498,177 @@ pub fn check_consensus(
     Ok(true)
 }
 
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConsensusProperty {
+    Agreement,
+    Validity,
+    Integrity,
+    Termination,
+}
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+pub enum Validity {
+    /// Every decided value was proposed by a correct node
+    Binary,
+    /// Every decided value was proposed by some node, and if all correct
+    /// nodes proposed the same value, then this value is decided
+    MultiValued,
+}
+
+/// Checks Agreement, Validity, Integrity and Termination for `correct_nodes`,
+/// where node `i` proposed `init_values[i]` (see `send_init_messages`).
+/// Nodes which haven't decided yet are stepped until their first local message.
+/// Returns every violation found, so that one run can break several properties.
+#[allow(dead_code)]
+pub fn get_consensus_violations(
+    sys: &mut System<JsonMessage>,
+    correct_nodes: &[String],
+    init_values: &[u64],
+    validity: Validity
+) -> Vec<(ConsensusProperty, String)> {
+    for node in correct_nodes.iter() {
+        if get_local_messages(sys, node).is_empty() {
+            let _ = sys.step_until_local_message(node);
+        }
+    }
+    evaluate_consensus(sys, correct_nodes, init_values, validity)
+}
+
+/// Same as `get_consensus_violations`, but judges the run as it is now,
+/// without stepping the system.
+#[allow(dead_code)]
+pub fn evaluate_consensus(
+    sys: &System<JsonMessage>,
+    correct_nodes: &[String],
+    init_values: &[u64],
+    validity: Validity
+) -> Vec<(ConsensusProperty, String)> {
+    let mut violations = Vec::new();
+
+    let mut correct_proposals = Vec::new();
+    for node in correct_nodes.iter() {
+        if let Some(value) = node.parse::<usize>().ok().and_then(|idx| init_values.get(idx)) {
+            correct_proposals.push(*value);
+        }
+    }
+
+    let mut decisions = Vec::<(String, u64)>::new();
+    for node in correct_nodes.iter() {
+        let messages = get_local_messages(sys, node);
+
+        let mut values = Vec::new();
+        for msg in messages.iter() {
+            if msg.tip != "RESULT" {
+                violations.push((
+                    ConsensusProperty::Integrity,
+                    format!("Node {}: unexpected local message {:?}", node, msg)
+                ));
+                continue;
+            }
+            match MessageResult::decode(msg) {
+                Ok(result) => values.push(result.value),
+                Err(e) => violations.push((ConsensusProperty::Integrity, format!("Node {}: {}", node, e))),
+            }
+        }
+
+        if values.is_empty() {
+            violations.push((ConsensusProperty::Termination, format!("Node {}: no decision", node)));
+            continue;
+        }
+        if values.len() > 1 {
+            violations.push((
+                ConsensusProperty::Integrity,
+                format!("Node {}: decided {} times ({:?})", node, values.len(), values)
+            ));
+        }
+        decisions.push((node.clone(), values[0]));
+    }
+
+    if decisions.iter().any(|(_, value)| *value != decisions[0].1) {
+        violations.push((ConsensusProperty::Agreement, format!("decisions differ: {:?}", decisions)));
+    }
+
+    for (node, value) in decisions.iter() {
+        let is_valid = match validity {
+            Validity::Binary => correct_proposals.contains(value),
+            Validity::MultiValued => {
+                let unanimous = correct_proposals.first()
+                    .filter(|first| correct_proposals.iter().all(|v| v == *first));
+                init_values.contains(value) && unanimous.is_none_or(|v| v == value)
+            }
+        };
+        if !is_valid {
+            violations.push((
+                ConsensusProperty::Validity,
+                format!(
+                    "Node {}: decided {}, correct nodes proposed {:?}",
+                    node, value, correct_proposals
+                )
+            ));
+        }
+    }
+
+    violations
+}
+
+#[allow(dead_code)]
+pub fn check_consensus_properties(
+    sys: &mut System<JsonMessage>,
+    correct_nodes: &[String],
+    init_values: &[u64],
+    validity: Validity
+) -> TestResult {
+    let violations = get_consensus_violations(sys, correct_nodes, init_values, validity);
+    if violations.is_empty() {
+        return Ok(true);
+    }
+    print_diagnostics(sys);
+    Err(violations
+        .iter()
+        .map(|(property, details)| format!("{:?} violated: {}", property, details))
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+/// Checks the safety properties of the run as it is now, without stepping
+/// the system: nodes which haven't decided yet are not a violation.
+#[allow(dead_code)]
+pub fn check_consensus_safety(
+    sys: &System<JsonMessage>,
+    correct_nodes: &[String],
+    init_values: &[u64],
+    validity: Validity
+) -> TestResult {
+    let violations: Vec<_> = evaluate_consensus(sys, correct_nodes, init_values, validity)
+        .into_iter()
+        .filter(|(property, _)| *property != ConsensusProperty::Termination)
+        .collect();
+    if violations.is_empty() {
+        return Ok(true);
+    }
+    print_diagnostics(sys);
+    Err(violations
+        .iter()
+        .map(|(property, details)| format!("{:?} violated: {}", property, details))
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
 #[allow(dead_code)]
 pub fn check_delivery(
+    sys: &mut System<JsonMessage>,
+    msg_type: &str,
+    nodes: &Vec<String>,
+    expected_result: Option<u64>
+) -> TestResult {
+    let result = check_delivered_values(sys, msg_type, nodes, expected_result);
+    with_diagnostics(sys, result)
+}
+
+#[allow(dead_code)]
+fn check_delivered_values(
     sys: &mut System<JsonMessage>,
     msg_type: &str,
     nodes: &Vec<String>,
@@ -142,8 +681,7 @@ pub fn check_delivery(
         assume!(messages.len() == 1, format!("Node {}: Wrong number of messages!", node))?;
         assume!(messages[0].tip == msg_type, format!("Node {}: Wrong message type!", node))?;
 
-        let data: Value = serde_json::from_str(&messages[0].data).unwrap();
-        let value = data["value"].as_u64().unwrap();
+        let value = messages::delivered_value(&messages[0]).map_err(|e| format!("Node {}: {}", node, e))?;
         if expected_result.is_none() {
             expected_result = Some(value);
         }
@@ -176,15 +714,15 @@ pub fn check_consensus_and_print_statistics(
     seed: u64,
     percentage_of_ones: u64
 ) -> TestResult {
-    assume!(check_consensus(sys, &nodes, expected_result).is_ok())?;
+    check_consensus(sys, &nodes, expected_result)?;
 
     let mut rounds_cnt = Vec::new();
 
     for node in nodes.iter() {
         let messages = get_local_messages(&sys, &node);
 
-        let data: Value = serde_json::from_str(&messages[0].data).unwrap();
-        let round = data["round"].as_u64().unwrap();
+        let result = MessageResult::decode(&messages[0]).map_err(|e| format!("Node {}: {}", node, e))?;
+        let round = result.round.ok_or_else(|| format!("Node {}: RESULT without round: {:?}", node, messages[0]))?;
         rounds_cnt.push(round);
     }
 
@@ -197,23 +735,235 @@ pub fn check_consensus_and_print_statistics(
         percentage_of_ones
     );
 
+    println!(
+        "messages: {} {} {} {} {}",
+        seed,
+        nodes.len(),
+        config.faulty_count,
+        percentage_of_ones,
+        format_message_counts(&get_message_counter(sys))
+    );
+
+    let latencies: Vec<f64> = get_decision_latencies(sys, nodes).iter().map(|(_, l)| *l).collect();
+    if let Some(stats) = LatencyStats::from_samples(&latencies) {
+        println!(
+            "latency: {} {} {} {:.3} {:.3} {:.3} {:.3} {}",
+            seed,
+            nodes.len(),
+            config.faulty_count,
+            stats.min,
+            stats.median,
+            stats.max,
+            stats.p99,
+            percentage_of_ones
+        );
+    }
+
     Ok(true)
 }
 
+/// Groups sent messages by sender, type, protocol instance and round.
+/// The instance is the `k` field (DBFT binary consensus) or the `sender`
+/// field (reliable broadcast inside DBFT) of the message.
 #[allow(dead_code)]
-pub fn build_system_with_custom_seed(config: &TestConfig, seed: u64) -> System<JsonMessage> {
-    let mut sys = System::with_seed(seed);
-    let mut node_ids = Vec::new();
-    for n in 0..config.node_count {
-        node_ids.push(format!("{}", n));
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MessageKey {
+    pub sender: String,
+    pub tip: String,
+    pub instance: Option<String>,
+    pub round: Option<u64>,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default)]
+pub struct MessageCounter {
+    counts: BTreeMap<MessageKey, u64>,
+}
+
+#[allow(dead_code)]
+impl MessageCounter {
+    pub fn from_events(events: &[events::Event]) -> Self {
+        let mut counter = Self::default();
+        for event in events.iter() {
+            if let events::Event::MessageSend { src, msg, .. } = event {
+                let fields = MessageFields::of(msg);
+                let key = MessageKey {
+                    sender: src.clone(),
+                    tip: msg.tip.clone(),
+                    instance: fields.k.map(|k| k.to_string()).or(fields.sender),
+                    round: fields.round,
+                };
+                *counter.counts.entry(key).or_insert(0) += 1;
+            }
+        }
+        counter
     }
-    for node_id in node_ids.iter() {
-        let node = config.node_factory.build(
-            node_id,
-            (node_id, node_ids.clone(), config.faulty_count, seed),
-            seed
+
+    pub fn counts(&self) -> &BTreeMap<MessageKey, u64> {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Number of messages whose key satisfies `filter`
+    pub fn count_where<F: Fn(&MessageKey) -> bool>(&self, filter: F) -> u64 {
+        self.counts.iter().filter(|(key, _)| filter(key)).map(|(_, cnt)| cnt).sum()
+    }
+
+    pub fn count_type(&self, tip: &str) -> u64 {
+        self.count_where(|key| key.tip == tip)
+    }
+
+    pub fn by_type(&self) -> BTreeMap<String, u64> {
+        self.group_by(|key| key.tip.clone())
+    }
+
+    pub fn by_sender(&self) -> BTreeMap<String, u64> {
+        self.group_by(|key| key.sender.clone())
+    }
+
+    pub fn by_round(&self) -> BTreeMap<Option<u64>, u64> {
+        self.group_by(|key| key.round)
+    }
+
+    pub fn by_instance(&self) -> BTreeMap<Option<String>, u64> {
+        self.group_by(|key| key.instance.clone())
+    }
+
+    fn group_by<K: Ord, F: Fn(&MessageKey) -> K>(&self, group: F) -> BTreeMap<K, u64> {
+        let mut result = BTreeMap::new();
+        for (key, cnt) in self.counts.iter() {
+            *result.entry(group(key)).or_insert(0) += cnt;
+        }
+        result
+    }
+}
+
+/// Highest round in the messages sent before `time` in the system built
+/// last, i.e. how many rounds the protocol went through by then.
+#[allow(dead_code)]
+pub fn rounds_before(time: f64) -> u64 {
+    assert!(events::is_capturing(), "rounds are counted from events::CapturingLogger, call init_logger first");
+    events::get_events()
+        .iter()
+        .filter_map(|event| match event {
+            events::Event::MessageSend { time: sent, msg, .. } if *sent < time => MessageFields::of(msg).round,
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Counts the messages sent in `sys`, the system built last, see `events::CapturingLogger`.
+/// Panics if the logger isn't installed or the captured sends disagree with
+/// the counts of dslib.
+#[allow(dead_code)]
+pub fn get_message_counter(sys: &System<JsonMessage>) -> MessageCounter {
+    assert!(events::is_capturing(), "messages are counted from events::CapturingLogger, call init_logger first");
+    let counter = MessageCounter::from_events(&events::get_events());
+    let sent: u64 = sys.get_node_ids().iter().map(|node| sys.get_sent_message_count(node)).sum();
+    assert_eq!(counter.total(), sent, "captured message sends disagree with the counts of dslib");
+    counter
+}
+
+#[allow(dead_code)]
+pub fn format_message_counts(counter: &MessageCounter) -> String {
+    let mut result = format!("total={}", counter.total());
+    for (tip, cnt) in counter.by_type() {
+        result += &format!(" {}={}", tip, cnt);
+    }
+    result
+}
+
+#[allow(dead_code)]
+pub fn print_message_counts(counter: &MessageCounter) {
+    println!("messages: {}", format_message_counts(counter));
+    for (round, cnt) in counter.by_round() {
+        if let Some(round) = round {
+            println!("messages: round {} {}", round, cnt);
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+pub struct LatencyStats {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+    pub p99: f64,
+}
+
+impl LatencyStats {
+    #[allow(dead_code)]
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mid = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 1 {
+            sorted[mid]
+        } else {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        };
+        // nearest-rank percentile
+        let p99_rank = ((sorted.len() as f64) * 0.99).ceil() as usize;
+        Some(Self {
+            min: sorted[0],
+            median,
+            max: sorted[sorted.len() - 1],
+            p99: sorted[p99_rank.max(1) - 1],
+        })
+    }
+}
+
+/// Simulated time between the node's INIT and its first RESULT local message,
+/// taken from the local events of `sys`. Nodes without RESULT are skipped.
+#[allow(dead_code)]
+pub fn get_decision_latencies(sys: &System<JsonMessage>, nodes: &[String]) -> Vec<(String, f64)> {
+    let mut latencies = Vec::new();
+    for node in nodes.iter() {
+        let mut init_time = 0.0;
+        for event in sys.get_local_events(node) {
+            let msg = match event.msg {
+                Some(msg) => msg,
+                None => continue,
+            };
+            match event.tip {
+                LocalEventType::LocalMessageReceive if msg.tip == "INIT" => init_time = event.time,
+                LocalEventType::LocalMessageSend if msg.tip == "RESULT" => {
+                    latencies.push((node.clone(), event.time - init_time));
+                    break;
+                }
+                _ => (),
+            }
+        }
+    }
+    latencies
+}
+
+#[allow(dead_code)]
+pub fn print_decision_latency(sys: &System<JsonMessage>, nodes: &[String]) {
+    let latencies = get_decision_latencies(sys, nodes);
+    for (node, latency) in latencies.iter() {
+        println!("latency: node {} {:.3}", node, latency);
+    }
+    let samples: Vec<f64> = latencies.iter().map(|(_, l)| *l).collect();
+    if let Some(stats) = LatencyStats::from_samples(&samples) {
+        println!(
+            "latency: min {:.3} median {:.3} max {:.3} p99 {:.3}",
+            stats.min, stats.median, stats.max, stats.p99
         );
-        sys.add_node(rc!(refcell!(node)));
     }
-    return sys;
+}
+
+#[allow(dead_code)]
+pub fn build_system_with_custom_seed(config: &TestConfig, seed: u64) -> System<JsonMessage> {
+    let mut sys = System::with_seed(seed);
+    build_nodes(&mut sys, config, &[], seed);
+    sys
 }